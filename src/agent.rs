@@ -1,9 +1,14 @@
 use std::rc::Rc;
 use std::cell::RefCell;
+use std::collections::HashMap;
+use hashmap_union::union_of;
 use weather::Weather;
 use transport_mode::TransportMode;
 use journey_type::JourneyType;
 use neighbourhood::Neighbourhood;
+use subculture::Subculture;
+use debug;
+use statistics;
 
 /// The agent in the model
 #[derive(PartialEq, Serialize, Deserialize)]
@@ -15,17 +20,30 @@ pub struct Agent {
     /// The ID of the neighbourhood
     pub neighbourhood_id: String,
 
-    /// The distance of the agent's commute (categorical).  
-    /// This may become deprecated, once commute_length_continuous
-    /// has a corresponding cost function
+    /// The subculture the agent belongs to
+    #[serde(skip)]
+    pub subculture: Rc<Subculture>,
+
+    /// The ID of the subculture
+    pub subculture_id: String,
+
+    /// The distance of the agent's commute (categorical)  
+    /// This is used by targets and the outputs, and by the decision model if there is no distance decay
     pub commute_length: JourneyType,
 
+    /// The distance of the agent's commute in metres, agents saved without it use their commute length
+    #[serde(default)]
+    pub commute_distance: Option<f64>,
+
     /// How sensitive the agent is to the weather
     pub weather_sensitivity: f32,
 
     /// How connected the agent is to its social network
     pub social_connectivity: f32,
 
+    /// How connected the agent is to its subculture
+    pub subculture_connectivity: f32,
+
     /// How connected the agent is to its neighbourhood
     pub neighbourhood_connectivity: f32,
 
@@ -35,6 +53,11 @@ pub struct Agent {
     /// How the agent travelled to work on the previous day
     pub last_mode: TransportMode,
 
+    /// An exponentially weighted average of how the agent has travelled to work,
+    /// from 0-1 for each mode, see Agent::update_habit
+    #[serde(default)]
+    pub habit: HashMap<TransportMode, f32>,
+
     /// Whether the agent owns a bike
     pub owns_bike: bool,
 
@@ -51,10 +74,147 @@ pub struct Agent {
 }
 
 impl Agent {
+    /// Remember how the agent travelled on the previous day  
+    /// This should be called for every agent before any agent chooses, so that
+    /// norms and congestion are calculated from the same day
+    pub fn update_last_mode(&mut self) {
+        self.last_mode = self.current_mode;
+    }
+
+    /// Update the habit average with the mode taken on the previous day  
+    /// The weight of the most recent day is 2 / (days_in_habit_average + 1), so that the most
+    /// recent days_in_habit_average days account for approximately 86% of the average
+    /// * days_in_habit_average: The number of days that dominate the habit average
+    pub fn update_habit(&mut self, days_in_habit_average: u32) {
+        let weight = 2.0 / (days_in_habit_average as f32 + 1.0);
+        let last_mode = self.last_mode;
+
+        for (mode, value) in self.habit.iter_mut() {
+            *value *= 1.0 - weight;
+            if *mode == last_mode {
+                *value += weight;
+            }
+        }
+
+        // If the agent has never used the mode before, it starts with just the weight of the last day
+        self.habit.entry(last_mode).or_insert(weight);
+    }
+
     /// Choose a mode of travel
     /// * weather: The current weather
     /// * change_in_weather: true if there has been a change in the weather, false otherwise
-    pub fn choose(&mut self, weather: &Weather, change_in_weather: bool) {
-        self.current_mode = TransportMode::Car;
+    /// * distance_decay: The distance at which the score of each mode halves, if the commute distance is used
+    pub fn choose(&mut self, weather: &Weather, change_in_weather: bool, distance_decay: Option<&HashMap<TransportMode, f64>>) {
+        // Weight the norms of the friends and neighbours, and the subculture by how connected the agent is to each
+        let peer_connectivity = self.social_connectivity + self.neighbourhood_connectivity;
+        let peer_norm: HashMap<TransportMode, f32> = statistics::calculate_norm(self, |a| a.last_mode)
+            .into_iter()
+            .map(|(mode, v)| (mode, v * peer_connectivity))
+            .collect();
+
+        let subculture_norm: HashMap<TransportMode, f32> = self.subculture
+            .desirability
+            .borrow()
+            .iter()
+            .map(|(&mode, &v)| (mode, v * self.subculture_connectivity))
+            .collect();
+
+        // Divide by the total connectivity, so that the norm stays between 0 and 1
+        let total_connectivity = peer_connectivity + self.subculture_connectivity;
+        let norm: HashMap<TransportMode, f32> = union_of(&peer_norm, &subculture_norm, |v1, v2| v1 + v2)
+            .into_iter()
+            .map(|(mode, v)| (mode, if total_connectivity > 0.0 { v / total_connectivity } else { 0.0 }))
+            .collect();
+
+        // Give the norm and the agent's habit equal weight
+        let influence: HashMap<TransportMode, f32> = union_of(&norm, &self.habit, |v1, v2| v1 + v2)
+            .into_iter()
+            .map(|(mode, v)| (mode, v / 2.0))
+            .collect();
+
+        // Score each mode that is available to the agent
+        let scores: HashMap<TransportMode, f32> = TransportMode::all()
+            .iter()
+            .filter(|&&mode| self.can_use(mode))
+            .map(|&mode| {
+                let score = influence.get(&mode).unwrap_or(&0.0)
+                    * self.neighbourhood.supportiveness.borrow().get(&mode).unwrap_or(&0.0)
+                    * self.neighbourhood.congestion_modifier.borrow().get(&mode).unwrap_or(&1.0)
+                    * self.weather_modifier(mode, weather, change_in_weather)
+                    * self.commute_length_modifier(mode, distance_decay);
+                (mode, score)
+            })
+            .collect();
+
+        debug::print_transport_mode_hashmap("Scores", &scores);
+
+        // Choose the highest scoring mode, in a tie prefer the current mode,
+        // otherwise the first in TransportMode::all()
+        let mut chosen_mode = None;
+        for mode in TransportMode::all().iter().filter(|mode| scores.contains_key(mode)) {
+            chosen_mode = match chosen_mode {
+                None => Some(*mode),
+                Some(best) => {
+                    if scores[mode] > scores[&best] || (scores[mode] == scores[&best] && *mode == self.current_mode) {
+                        Some(*mode)
+                    } else {
+                        Some(best)
+                    }
+                }
+            };
+        }
+
+        // Walk and PublicTransport are always available, so a mode is always chosen
+        self.current_mode = chosen_mode.unwrap();
+    }
+
+    /// Whether the agent is able to travel by a mode
+    /// * mode: The mode of travel
+    /// * Returns: false if the mode needs a car or bike that the agent does not own, true otherwise
+    fn can_use(&self, mode: TransportMode) -> bool {
+        match mode {
+            TransportMode::Car => self.owns_car,
+            TransportMode::Cycle => self.owns_bike,
+            _ => true
+        }
+    }
+
+    /// How much the weather discourages a mode  
+    /// A mode is discouraged by the agent's weather sensitivity multiplied by the weather's discouragement
+    /// of the mode, on the day the weather changes the agent reacts fully, if the weather stays the same
+    /// the agent has adapted to half of it
+    /// * mode: The mode of travel
+    /// * weather: The current weather
+    /// * change_in_weather: true if there has been a change in the weather, false otherwise
+    /// * Returns: A modifier from 0-1, where 1 is no effect
+    fn weather_modifier(&self, mode: TransportMode, weather: &Weather, change_in_weather: bool) -> f32 {
+        let discouragement = weather.discouragement.get(&mode).unwrap_or(&0.0) * self.weather_sensitivity;
+
+        if change_in_weather {
+            1.0 - discouragement
+        } else {
+            1.0 - discouragement / 2.0
+        }
+    }
+
+    /// How suitable a mode is for the length of the agent's commute  
+    /// With distance decay, and a commute distance, the modifier halves every half distance of the mode,
+    /// otherwise it depends on the commute length
+    /// * mode: The mode of travel
+    /// * distance_decay: The distance at which the modifier of each mode halves
+    /// * Returns: A modifier from 0-1, where 1 is no effect
+    fn commute_length_modifier(&self, mode: TransportMode, distance_decay: Option<&HashMap<TransportMode, f64>>) -> f32 {
+        if let (Some(distance_decay), Some(distance)) = (distance_decay, self.commute_distance) {
+            return distance_decay
+                .get(&mode)
+                .map_or(1.0, |half_distance| 0.5f64.powf(distance / half_distance) as f32);
+        }
+
+        match (self.commute_length, mode) {
+            (JourneyType::CityCommute, TransportMode::Walk) => 0.1,
+            (JourneyType::DistantCommute, TransportMode::Walk) => 0.0,
+            (JourneyType::DistantCommute, TransportMode::Cycle) => 0.1,
+            _ => 1.0
+        }
     }
 }
@@ -1,10 +1,10 @@
-use rand;
 use std::collections::HashMap;
+use std::collections::BTreeMap;
 use std::io::Write;
 use std::io::Read;
 use rand::distributions;
 use rand::distributions::Distribution;
-use rand::thread_rng;
+use rand::Rng;
 use rand::seq::sample_slice_ref;
 use std::rc::Rc;
 use std::cell::RefCell;
@@ -12,37 +12,87 @@ use std::fs::File;
 use transport_mode::TransportMode;
 use journey_type::JourneyType;
 use neighbourhood::Neighbourhood;
+use subculture::Subculture;
 use scenario::Scenario;
 use agent::Agent;
 use gaussian;
+use initial_mode;
+use initial_mode::Ownership;
 use serde_yaml;
+use error::Error;
+use error::Problem;
 
 /// Loads unlinked agents from a YAML file
 /// * file: The file to load from
+/// * agents_file: The path of the file, problems are reported in it
 /// * neighbourhoods: The neighbourhoods in the scenario
-/// * Returns: The loaded agents
-pub fn load_unlinked_agents_from_file(mut file: File, neighbourhoods: &[Rc<Neighbourhood>]) -> Vec<Rc<RefCell<Agent>>> {
-        info!("Loading parameters from file");
+/// * subcultures: The subcultures in the scenario
+/// * Returns: The loaded agents, or an Error if the file could not be read or parsed,
+///   or an agent's neighbourhood or subculture is not in the scenario
+pub fn load_unlinked_agents_from_file(
+    mut file: File,
+    agents_file: &str,
+    neighbourhoods: &[Rc<Neighbourhood>],
+    subcultures: &[Rc<Subculture>]) -> Result<Vec<Rc<RefCell<Agent>>>, Error>
+{
+        info!("Loading agents from file");
         let mut file_contents = String::new();
 
-        file.read_to_string(&mut file_contents)
-            .expect("There was an error reading the file");
+        file.read_to_string(&mut file_contents)?;
 
-        let mut residents: Vec<Rc<RefCell<Agent>>> = serde_yaml::from_slice(file_contents.as_bytes())
-            .expect("There was an error parsing the file");
+        let mut residents: Vec<Rc<RefCell<Agent>>> = serde_yaml::from_slice(file_contents.as_bytes())?;
 
         let neighbourhoods_kvp: HashMap<String, Rc<Neighbourhood>> = neighbourhoods
             .iter()
             .map(|neighbourhood| (neighbourhood.id.clone(), Rc::clone(neighbourhood)))
             .collect();
 
-        for agent in residents.iter_mut() {
-            let neighbourhood = Rc::clone(neighbourhoods_kvp.get(&agent.borrow().neighbourhood_id).expect("Agent not found"));
-            neighbourhood.residents.borrow_mut().push(Rc::clone(agent));
-            agent.borrow_mut().neighbourhood = neighbourhood;
+        let subcultures_kvp: HashMap<String, Rc<Subculture>> = subcultures
+            .iter()
+            .map(|subculture| (subculture.id.clone(), Rc::clone(subculture)))
+            .collect();
+
+        // The agents with each missing neighbourhood and subculture id, by the index of the first and the count
+        let mut missing_neighbourhoods: BTreeMap<String, (usize, usize)> = BTreeMap::new();
+        let mut missing_subcultures: BTreeMap<String, (usize, usize)> = BTreeMap::new();
+        for (i, agent) in residents.iter_mut().enumerate() {
+            // Agents saved without a habit start with the habit of their current mode
+            if agent.borrow().habit.is_empty() {
+                let current_mode = agent.borrow().current_mode;
+                agent.borrow_mut().habit = hashmap! { current_mode => 1.0 };
+            }
+
+            let neighbourhood_id = agent.borrow().neighbourhood_id.clone();
+            match neighbourhoods_kvp.get(&neighbourhood_id) {
+                Some(neighbourhood) => {
+                    neighbourhood.residents.borrow_mut().push(Rc::clone(agent));
+                    agent.borrow_mut().neighbourhood = Rc::clone(neighbourhood);
+                },
+                None => missing_neighbourhoods.entry(neighbourhood_id).or_insert((i, 0)).1 += 1
+            }
+
+            let subculture_id = agent.borrow().subculture_id.clone();
+            match subcultures_kvp.get(&subculture_id) {
+                Some(subculture) => agent.borrow_mut().subculture = Rc::clone(subculture),
+                None => missing_subcultures.entry(subculture_id).or_insert((i, 0)).1 += 1
+            }
         }
 
-        residents
+        let mut problems: Vec<Problem> = missing_neighbourhoods
+            .into_iter()
+            .map(|(id, (first, count))| Problem::new(agents_file, format!("[{}].neighbourhood_id", first),
+                format!("there is no neighbourhood {} in the scenario, {} agent(s) live in it", id, count)))
+            .collect();
+        problems.extend(missing_subcultures
+            .into_iter()
+            .map(|(id, (first, count))| Problem::new(agents_file, format!("[{}].subculture_id", first),
+                format!("there is no subculture {} in the scenario, {} agent(s) belong to it", id, count))));
+
+        if problems.is_empty() {
+            Ok(residents)
+        } else {
+            Err(Error::Invalid(problems))
+        }
 }
 
 /// Saves the agents to a file
@@ -57,25 +107,35 @@ fn save_agents(mut file: File, agents: &[Rc<RefCell<Agent>>]) {
 /// * file: The file to save them to
 /// * scenario: The scenario of the simulation
 /// * social_connectivity: How connected the agent is to its social network
+/// * subculture_connectivity: How connected the agent is to its subculture
 /// * neighbourhood_connectivity: How connected the agent is to its neighbourhood
 /// * number_of_people: The number of agents to generate
 /// * distributions: JourneyType distributions, used in gmm
+/// * commute_length_thresholds: The distances at which a LocalCommute becomes a CityCommute, and a CityCommute becomes a DistantCommute
+/// * rng: The random number generator
 /// * Returns: The created agents
-pub fn generate_and_save_agents(
+#[allow(clippy::too_many_arguments)]
+pub fn generate_and_save_agents<R: Rng>(
     file: File,
     scenario: &Scenario,
     social_connectivity: f32,
+    subculture_connectivity: f32,
     neighbourhood_connectivity: f32,
     number_of_people: u32,
-    distributions: Vec<(f64, f64, f64)>
+    distributions: Vec<(f64, f64, f64)>,
+    commute_length_thresholds: (f64, f64),
+    rng: &mut R
     ) -> Vec<Rc<RefCell<Agent>>>
 {
     let agents = generate_unlinked_agents(
         scenario, 
         social_connectivity, 
+        subculture_connectivity, 
         neighbourhood_connectivity, 
         number_of_people, 
-        distributions);
+        distributions,
+        commute_length_thresholds,
+        rng);
     
     save_agents(file, &agents);
 
@@ -90,21 +150,28 @@ pub fn generate_and_save_agents(
 /// See [here](https://doc.rust-lang.org/book/second-edition/ch15-05-interior-mutability.html) for more details
 /// * scenario: The scenario of the simulation
 /// * social_connectivity: How connected the agent is to its social network
+/// * subculture_connectivity: How connected the agent is to its subculture
 /// * neighbourhood_connectivity: How connected the agent is to its neighbourhood
 /// * number_of_people: The number of agents to generate
 /// * distributions: JourneyType distributions, used in gmm
+/// * commute_length_thresholds: The distances at which a LocalCommute becomes a CityCommute, and a CityCommute becomes a DistantCommute
+/// * rng: The random number generator
 /// * Returns: The created agents
-fn generate_unlinked_agents(scenario: &Scenario,
+#[allow(clippy::too_many_arguments)]
+fn generate_unlinked_agents<R: Rng>(scenario: &Scenario,
           social_connectivity: f32,
+          subculture_connectivity: f32,
           neighbourhood_connectivity: f32,
           number_of_people: u32,
-          distributions: Vec<(f64, f64, f64)>) -> Vec<Rc<RefCell<Agent>>> {
+          distributions: Vec<(f64, f64, f64)>,
+          commute_length_thresholds: (f64, f64),
+          rng: &mut R) -> Vec<Rc<RefCell<Agent>>> {
     // Create an empty vec to store agents
     let mut residents = Vec::new();
     // Create self.number_of_people unlinked agents
     for _ in 0..number_of_people {
         let agent = create_unlinked_agent(scenario, social_connectivity,
-            neighbourhood_connectivity);
+            subculture_connectivity, neighbourhood_connectivity, rng);
 
         let rc_agent = Rc::new(RefCell::new(agent));
         
@@ -129,23 +196,21 @@ fn generate_unlinked_agents(scenario: &Scenario,
     // scope then &residents and &mut residents are in scope at the same time.
     {
         // Give people cars
-        let mut rng = thread_rng();
-        let sample = sample_slice_ref(&mut rng, &residents, scenario.number_of_cars as usize);
+        let sample = sample_slice_ref(rng, &residents, scenario.number_of_cars as usize);
         sample
             .iter()
             .for_each(|agent| agent.borrow_mut().owns_car = true);
     }
     {
         // Give people bikes
-        let mut rng = thread_rng();
-        let sample = sample_slice_ref(&mut rng, &residents, scenario.number_of_cars as usize);
+        let sample = sample_slice_ref(rng, &residents, scenario.number_of_bikes as usize);
         sample
             .iter()
             .for_each(|agent| agent.borrow_mut().owns_bike = true);
     }
 
     // Get random commute distances
-    let commute_distances: Vec<f64> = gaussian::get_samples_from_gmm(number_of_people as usize, distributions)
+    let commute_distances: Vec<f64> = gaussian::get_samples_from_gmm(number_of_people as usize, distributions, rng)
         .into_iter()
         .map(|x| x.abs())
         .collect();
@@ -154,27 +219,27 @@ fn generate_unlinked_agents(scenario: &Scenario,
     commute_distances
         .iter()
         .zip(residents.iter())
-        .for_each(|(distance, agent)| {
+        .for_each(|(&distance, agent)| {
             let agent_ref = &mut agent.borrow_mut();
-            // Assign categorical distance
-            if *distance < 4241.0 {
-                agent_ref.commute_length = JourneyType::LocalCommute
-            } else if *distance < 19457.0 {
-                agent_ref.commute_length = JourneyType::CityCommute
-            } else {
-                agent_ref.commute_length = JourneyType::DistantCommute
-            }
+            // Keep the continuous distance, and assign the categorical distance
+            agent_ref.commute_distance = Some(distance);
+            agent_ref.commute_length = JourneyType::from_distance(distance, commute_length_thresholds);
         });
 
-    // For each agent, choose an initial mode
-    for agent in residents.iter() {
+    // For each agent, choose an initial mode, now their commute distance is known
+    for (distance, agent) in commute_distances.iter().zip(residents.iter()) {
         let mut borrowed_agent = agent.borrow_mut();
-        let new_mode = choose_initial_mode(
-            borrowed_agent.owns_car, 
-            borrowed_agent.owns_bike);
+        let probabilities = initial_mode::probabilities_for(
+            scenario,
+            &borrowed_agent.neighbourhood,
+            borrowed_agent.commute_length);
+        let new_mode = initial_mode::choose(
+            &probabilities.at_distance(Ownership::of(borrowed_agent.owns_car, borrowed_agent.owns_bike), *distance),
+            rng);
         
         borrowed_agent.current_mode = new_mode;
         borrowed_agent.last_mode = new_mode;
+        borrowed_agent.habit = hashmap! { new_mode => 1.0 };
     }
 
     // Return the created agents
@@ -184,16 +249,23 @@ fn generate_unlinked_agents(scenario: &Scenario,
 /// Create an unlinked agent, that does not own a bike or a car, without a current mode, and without a commute length
 /// * scenario: The scenario of the simulation
 /// * social_connectivity: How connected the agent is to its social network
+/// * subculture_connectivity: How connected the agent is to its subculture
 /// * neighbourhood_connectivity: How connected the agent is to its neighbourhood
+/// * rng: The random number generator
 /// * Returns: The created agent
-fn create_unlinked_agent(scenario: &Scenario,
+fn create_unlinked_agent<R: Rng>(scenario: &Scenario,
                          social_connectivity: f32,
-                         neighbourhood_connectivity: f32) -> Agent {
+                         subculture_connectivity: f32,
+                         neighbourhood_connectivity: f32,
+                         rng: &mut R) -> Agent {
     // Choose a neighbourhood
-    let neighbourhood = choose_neighbourhood(scenario);
+    let neighbourhood = choose_neighbourhood(scenario, rng);
+
+    // Choose a subculture
+    let subculture = choose_subculture(scenario, rng);
 
     // Weather sensitivity is currently fixed
-    let weather_sensitivity = rand::random::<f32>();
+    let weather_sensitivity = rng.gen::<f32>();
 
     // Use a placeholder transport mode
     let current_mode: TransportMode = TransportMode::PublicTransport;
@@ -203,12 +275,17 @@ fn create_unlinked_agent(scenario: &Scenario,
     Agent {
         neighbourhood_id: neighbourhood.id.clone(),
         neighbourhood,
+        subculture_id: subculture.id.clone(),
+        subculture,
         commute_length: JourneyType::LocalCommute,
+        commute_distance: None,
         weather_sensitivity,
-        social_connectivity: social_connectivity,
-        neighbourhood_connectivity: neighbourhood_connectivity,
+        social_connectivity,
+        subculture_connectivity,
+        neighbourhood_connectivity,
         current_mode,
         last_mode,
+        habit: HashMap::new(),
         owns_bike: false,
         owns_car: false,
         social_network: Vec::new(),
@@ -218,57 +295,26 @@ fn create_unlinked_agent(scenario: &Scenario,
 
 /// Choose a random neighbourhood, equal chance of each
 /// * scenario: The scenario of the simulation
+/// * rng: The random number generator
 /// * Returns: The chosen neighbourhood
-fn choose_neighbourhood(scenario: &Scenario) -> Rc<Neighbourhood> {
+fn choose_neighbourhood<R: Rng>(scenario: &Scenario, rng: &mut R) -> Rc<Neighbourhood> {
     let mut weighted: Vec<distributions::Weighted<Rc<Neighbourhood>>> = scenario.neighbourhoods
         .iter()
         .map(|s: &Rc<Neighbourhood>| distributions::Weighted {weight: 1, item: Rc::clone(s)})
         .collect();
     let weighted_choice = distributions::WeightedChoice::new(&mut weighted);
-    weighted_choice.sample(&mut thread_rng())
+    weighted_choice.sample(rng)
 }
 
-/// Choose an initial mode
-/// * owns_car: whether the agent owns a car
-/// * owns_bike: whether the agent owns a bike
-/// * Returns: The chosen transport mode
-fn choose_initial_mode(owns_car: bool, owns_bike: bool) -> TransportMode {
-    if owns_car && owns_bike {
-        let randfloat = rand::random::<f64>();
-        if randfloat < 0.4 {
-            TransportMode::Car
-        } else if randfloat < 0.7 {
-            TransportMode::Cycle
-        } else if randfloat < 0.85 {
-            TransportMode::Walk
-        } else {
-            TransportMode::PublicTransport
-        }
-    } else if owns_car {
-        let randfloat = rand::random::<f64>();
-        if randfloat < 0.57 {
-            TransportMode::Car
-        }
-        else if randfloat < 0.79 {
-            TransportMode::Walk
-        } else {
-            TransportMode::PublicTransport
-        }
-    } else if owns_bike {
-        let randfloat = rand::random::<f64>();
-        if randfloat < 0.5 {
-            TransportMode::Cycle
-        } else if randfloat < 0.75 {
-            TransportMode::Walk
-        } else {
-            TransportMode::PublicTransport
-        }
-    } else {
-        let randfloat = rand::random::<f64>();
-        if randfloat < 0.5 {
-            TransportMode::Walk
-        } else {
-            TransportMode::PublicTransport
-        }
-    }
-}
\ No newline at end of file
+/// Choose a random subculture, equal chance of each
+/// * scenario: The scenario of the simulation
+/// * rng: The random number generator
+/// * Returns: The chosen subculture
+fn choose_subculture<R: Rng>(scenario: &Scenario, rng: &mut R) -> Rc<Subculture> {
+    let mut weighted: Vec<distributions::Weighted<Rc<Subculture>>> = scenario.subcultures
+        .iter()
+        .map(|s: &Rc<Subculture>| distributions::Weighted {weight: 1, item: Rc::clone(s)})
+        .collect();
+    let weighted_choice = distributions::WeightedChoice::new(&mut weighted);
+    weighted_choice.sample(rng)
+}
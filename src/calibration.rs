@@ -0,0 +1,392 @@
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::collections::HashSet;
+use std::rc::Rc;
+use std::fs::File;
+use std::io::prelude::*;
+use serde_yaml;
+use error::Error;
+use error::Problem;
+use journey_type::JourneyType;
+use initial_mode;
+use initial_mode::Ownership;
+use scenario::Scenario;
+use summary;
+use sweep;
+use sweep::Range;
+use transport_mode::TransportMode;
+
+/// The observed mode shares a calibration fits to, and how the parameters are searched
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Calibration {
+    /// A CSV file of the observed mode shares, with the columns Neighbourhood,CommuteLength,Mode,Share
+    pub observed: String,
+
+    /// How the parameters are searched
+    pub method: Method,
+
+    /// The range of each parameter that is fitted, the same parameters as a sweep can be fitted
+    #[serde(default)]
+    pub ranges: Vec<Range>,
+
+    /// The range of each initial mode probability that is fitted
+    #[serde(default)]
+    pub initial_modes: Vec<InitialModeRange>,
+
+    /// The most points Nelder-Mead simulates
+    #[serde(default = "default_evaluations")]
+    pub evaluations: u32,
+
+    /// The number of points approximate Bayesian computation samples, using a Latin hypercube
+    #[serde(default = "default_samples")]
+    pub samples: u32,
+
+    /// The number of closest samples approximate Bayesian computation accepts
+    #[serde(default = "default_accepted")]
+    pub accepted: u32
+}
+
+/// The range of an initial mode probability that is fitted, the other probabilities are not changed
+#[derive(Clone, Serialize, Deserialize)]
+pub struct InitialModeRange {
+    /// The ID of the neighbourhood, if left out the probability of the scenario is fitted
+    #[serde(default)]
+    pub neighbourhood: Option<String>,
+    /// The commute length of the agents
+    pub commute_length: JourneyType,
+    /// Whether the agents own a car and a bike
+    pub ownership: Ownership,
+    /// The mode
+    pub mode: TransportMode,
+    /// The smallest probability
+    pub minimum: f64,
+    /// The largest probability
+    pub maximum: f64
+}
+
+/// The default most points Nelder-Mead simulates
+fn default_evaluations() -> u32 {
+    40
+}
+
+/// The default number of points approximate Bayesian computation samples
+fn default_samples() -> u32 {
+    40
+}
+
+/// The default number of samples approximate Bayesian computation accepts
+fn default_accepted() -> u32 {
+    5
+}
+
+/// How the parameters of a calibration are searched
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Method {
+    /// The Nelder-Mead simplex method, minimising the distance from the observed shares
+    NelderMead,
+    /// Rejection sampling, accepting the samples closest to the observed shares
+    ApproximateBayesianComputation
+}
+
+/// The share of agents taking each mode, by (Neighbourhood ID, commute length, mode)
+pub type ModeShares = BTreeMap<(String, String, String), f64>;
+
+impl Calibration {
+    /// Loads a calibration from a file
+    /// * file: A YAML file containing the Calibration
+    /// * Returns: The calibration, or an Error if the file could not be read or parsed
+    pub fn from_file(mut file: File) -> Result<Self, Error> {
+        let mut file_contents = String::new();
+        file.read_to_string(&mut file_contents)?;
+        Ok(serde_yaml::from_slice(file_contents.as_bytes())?)
+    }
+
+    /// Checks the calibration
+    /// * file: The file the calibration was loaded from
+    /// * Returns: Every problem found
+    pub fn check(&self, file: &str) -> Vec<Problem> {
+        let mut problems = Vec::new();
+
+        // The parameter ranges may be left out when initial mode probabilities are fitted, otherwise one is needed
+        if !self.ranges.is_empty() || self.initial_modes.is_empty() {
+            sweep::check_ranges(&self.ranges, file, &mut problems);
+        }
+
+        let mut probabilities = HashSet::new();
+        for (i, range) in self.initial_modes.iter().enumerate() {
+            if !probabilities.insert((&range.neighbourhood, range.commute_length, range.ownership, range.mode)) {
+                problems.push(Problem::new(file, format!("initial_modes[{}]", i), format!("{} is used more than once", range.name())));
+            }
+            if range.minimum > range.maximum {
+                problems.push(Problem::new(file, format!("initial_modes[{}]", i), "minimum should not be more than maximum"));
+            }
+            if range.minimum < 0.0 {
+                problems.push(Problem::new(file, format!("initial_modes[{}].minimum", i), "should not be negative"));
+            }
+            if !range.ownership.can_use(range.mode) {
+                problems.push(Problem::new(file, format!("initial_modes[{}].mode", i),
+                    format!("{:?} cannot be used by agents with the ownership {}", range.mode, range.ownership.name())));
+            }
+        }
+
+        match self.method {
+            Method::NelderMead => if self.evaluations == 0 {
+                problems.push(Problem::new(file, "evaluations", "should be at least 1"));
+            },
+            Method::ApproximateBayesianComputation => if self.accepted == 0 || self.accepted > self.samples {
+                problems.push(Problem::new(file, "accepted", "should be at least 1, and no more than samples"));
+            }
+        }
+
+        problems
+    }
+}
+
+impl InitialModeRange {
+    /// The name of the probability, as in the scenario file
+    pub fn name(&self) -> String {
+        let probability = format!("initial_modes.{:?}.{}.{:?}", self.commute_length, self.ownership.name(), self.mode);
+        match self.neighbourhood {
+            Some(ref id) => format!("neighbourhoods.{}.{}", id, probability),
+            None => probability
+        }
+    }
+
+    /// The value of the probability in a scenario
+    /// * scenario: The scenario, which has the neighbourhood
+    /// * Returns: The probability, before it is scaled with the other probabilities
+    pub fn value(&self, scenario: &Scenario) -> f64 {
+        let probabilities = match self.neighbourhood {
+            Some(ref id) => {
+                let neighbourhood = scenario.neighbourhoods.iter().find(|neighbourhood| &neighbourhood.id == id).unwrap();
+                initial_mode::probabilities_for(scenario, neighbourhood, self.commute_length)
+            },
+            None => scenario.initial_modes.get(&self.commute_length).cloned().unwrap_or_default()
+        };
+        probabilities.for_ownership(self.ownership).get(&self.mode).cloned().unwrap_or(0.0)
+    }
+}
+
+/// The scenario at a point
+/// * ranges: The ranges of the initial mode probabilities fitted
+/// * base: The scenario, which has the neighbourhood of every range
+/// * unit_point: The point, with a value from 0-1 for each range
+/// * Returns: The scenario, a neighbourhood without its own probabilities starts with those of the scenario
+pub fn scenario_at(ranges: &[InitialModeRange], base: &Scenario, unit_point: &[f64]) -> Scenario {
+    let mut scenario = base.clone();
+
+    // The probabilities of the scenario are set first, so neighbourhoods start with their fitted values
+    let mut values: Vec<(&InitialModeRange, f64)> = ranges
+        .iter()
+        .zip(unit_point.iter())
+        .map(|(range, &u)| (range, range.minimum + u * (range.maximum - range.minimum)))
+        .collect();
+    values.sort_by_key(|&(range, _)| range.neighbourhood.is_some());
+
+    for (range, value) in values {
+        let probabilities = match range.neighbourhood {
+            Some(ref id) => {
+                let i = scenario.neighbourhoods.iter().position(|neighbourhood| &neighbourhood.id == id).unwrap();
+                let inherited = initial_mode::probabilities_for(&scenario, &scenario.neighbourhoods[i], range.commute_length);
+                Rc::make_mut(&mut scenario.neighbourhoods[i]).initial_modes.entry(range.commute_length).or_insert(inherited)
+            },
+            None => scenario.initial_modes.entry(range.commute_length).or_default()
+        };
+        probabilities.for_ownership_mut(range.ownership).insert(range.mode, value);
+    }
+
+    scenario
+}
+
+/// Reads mode shares from a CSV file, observed shares and simulated shares have the same columns
+/// * path: The path of the file, with the columns Neighbourhood,CommuteLength,Mode,Share
+/// * Returns: The mode shares, or an Error if a row is not valid
+pub fn read_mode_shares(path: &str) -> Result<ModeShares, Error> {
+    let (header, rows) = summary::read_output(path)?;
+    let neighbourhood_column = summary::find_column(&header, "Neighbourhood", path)?;
+    let commute_length_column = summary::find_column(&header, "CommuteLength", path)?;
+    let mode_column = summary::find_column(&header, "Mode", path)?;
+    let share_column = summary::find_column(&header, "Share", path)?;
+
+    let commute_lengths: Vec<String> = JourneyType::all().iter().map(|journey_type| format!("{:?}", journey_type)).collect();
+    let modes: Vec<String> = TransportMode::all().iter().map(|mode| format!("{:?}", mode)).collect();
+
+    let mut problems = Vec::new();
+    let mut mode_shares = ModeShares::new();
+    for (i, row) in rows.iter().enumerate() {
+        let location = format!("row {}", i + 2);
+        let column = |column: usize| row.get(column).cloned().unwrap_or_default();
+
+        if !commute_lengths.contains(&column(commute_length_column)) {
+            problems.push(Problem::new(path, location.as_str(),
+                format!("the commute length should be one of {}", commute_lengths.join(", "))));
+        }
+        if !modes.contains(&column(mode_column)) {
+            problems.push(Problem::new(path, location.as_str(), format!("the mode should be one of {}", modes.join(", "))));
+        }
+        match column(share_column).parse::<f64>() {
+            Ok(share) if (0.0..=1.0).contains(&share) => {
+                mode_shares.insert((column(neighbourhood_column), column(commute_length_column), column(mode_column)), share);
+            },
+            _ => problems.push(Problem::new(path, location.as_str(), "the share should be a number from 0-1"))
+        }
+    }
+
+    if problems.is_empty() {
+        Ok(mode_shares)
+    } else {
+        Err(Error::Invalid(problems))
+    }
+}
+
+/// The mean mode shares of several simulations
+/// * paths: The mode share files of the simulations
+/// * Returns: The mean share of each mode, groups missing from a simulation have a share of 0 in it
+pub fn mean_mode_shares(paths: &[String]) -> Result<ModeShares, Error> {
+    let mut mean = ModeShares::new();
+    for path in paths.iter() {
+        for (key, share) in read_mode_shares(path)? {
+            *mean.entry(key).or_insert(0.0) += share / paths.len() as f64;
+        }
+    }
+    Ok(mean)
+}
+
+/// The distance between simulated and observed mode shares
+/// * observed: The observed shares
+/// * simulated: The simulated shares, groups that are not simulated have a share of 0
+/// * Returns: The root mean square error of the simulated shares
+pub fn distance(observed: &ModeShares, simulated: &ModeShares) -> f64 {
+    let squared_error: f64 = observed
+        .iter()
+        .map(|(key, share)| (simulated.get(key).cloned().unwrap_or(0.0) - share).powi(2))
+        .sum();
+    (squared_error / observed.len().max(1) as f64).sqrt()
+}
+
+/// A report of how well simulated mode shares fit the observed shares
+/// * observed: The observed shares
+/// * simulated: The simulated shares
+/// * Returns: A CSV of the observed and simulated share of each group and mode, and their difference
+pub fn fit_report(observed: &ModeShares, simulated: &ModeShares) -> String {
+    let mut report = String::from("Neighbourhood,CommuteLength,Mode,Observed,Simulated,Difference\n");
+    for ((neighbourhood, commute_length, mode), share) in observed.iter() {
+        let simulated_share = simulated
+            .get(&(neighbourhood.clone(), commute_length.clone(), mode.clone()))
+            .cloned()
+            .unwrap_or(0.0);
+        report.push_str(&format!("{},{},{},{},{},{}\n",
+            neighbourhood, commute_length, mode, share, simulated_share, simulated_share - share));
+    }
+    report
+}
+
+/// Minimises a function of a point in the unit hypercube using the Nelder-Mead simplex method  
+/// The simplex starts at the centre of the hypercube, and points outside it are moved to its edge
+/// * dimensions: The number of dimensions
+/// * evaluations: The most times the function is evaluated, once a step has started it is finished
+/// * f: The function, which is deterministic as every point uses the same random numbers
+/// * Returns: Nothing if successful, otherwise the Error evaluating the function
+pub fn nelder_mead<F>(dimensions: usize, evaluations: u32, mut f: F) -> Result<(), Error>
+    where F: FnMut(&[f64]) -> Result<f64, Error>
+{
+    let count = Cell::new(0);
+    let mut evaluate = |point: Vec<f64>| -> Result<(Vec<f64>, f64), Error> {
+        count.set(count.get() + 1);
+        let point: Vec<f64> = point.into_iter().map(|x| x.clamp(0.0, 1.0)).collect();
+        let value = f(&point)?;
+        Ok((point, value))
+    };
+
+    // Moves from a point, towards or away from another
+    let towards = |from: &[f64], to: &[f64], amount: f64| -> Vec<f64> {
+        from.iter().zip(to.iter()).map(|(a, b)| a + amount * (b - a)).collect()
+    };
+
+    let centre = vec![0.5; dimensions];
+    let mut simplex = vec![evaluate(centre.clone())?];
+    for i in 0..dimensions {
+        let mut vertex = centre.clone();
+        vertex[i] += 0.25;
+        simplex.push(evaluate(vertex)?);
+    }
+
+    while count.get() < evaluations {
+        simplex.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+        // Stop once the simplex has shrunk to a point
+        let size = simplex
+            .iter()
+            .skip(1)
+            .flat_map(|vertex| vertex.0.iter().zip(simplex[0].0.iter()).map(|(a, b)| (a - b).abs()))
+            .fold(0.0, f64::max);
+        if size < 1e-3 {
+            break;
+        }
+
+        let best = simplex[0].1;
+        let second_worst = simplex[dimensions - 1].1;
+        let (worst_point, worst) = simplex[dimensions].clone();
+        let centroid: Vec<f64> = (0..dimensions)
+            .map(|i| simplex[..dimensions].iter().map(|vertex| vertex.0[i]).sum::<f64>() / dimensions as f64)
+            .collect();
+
+        let reflected = evaluate(towards(&centroid, &worst_point, -1.0))?;
+        if reflected.1 < best {
+            let expanded = evaluate(towards(&centroid, &worst_point, -2.0))?;
+            simplex[dimensions] = if expanded.1 < reflected.1 { expanded } else { reflected };
+        } else if reflected.1 < second_worst {
+            simplex[dimensions] = reflected;
+        } else {
+            let contracted = if reflected.1 < worst {
+                evaluate(towards(&centroid, &reflected.0, 0.5))?
+            } else {
+                evaluate(towards(&centroid, &worst_point, 0.5))?
+            };
+
+            if contracted.1 < reflected.1.min(worst) {
+                simplex[dimensions] = contracted;
+            } else {
+                // Shrink every vertex towards the best
+                let best_point = simplex[0].0.clone();
+                for vertex in simplex.iter_mut().skip(1) {
+                    *vertex = evaluate(towards(&best_point, &vertex.0, 0.5))?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nelder_mead_finds_the_minimum_of_a_quadratic() {
+        let mut best = (Vec::new(), f64::INFINITY);
+        nelder_mead(2, 200, |point| {
+            let value = (point[0] - 0.3).powi(2) + 2.0 * (point[1] - 0.7).powi(2);
+            if value < best.1 {
+                best = (point.to_vec(), value);
+            }
+            Ok(value)
+        }).unwrap();
+
+        assert!((best.0[0] - 0.3).abs() < 1e-3);
+        assert!((best.0[1] - 0.7).abs() < 1e-3);
+    }
+
+    #[test]
+    fn nelder_mead_stops_after_the_evaluations() {
+        let mut count = 0;
+        nelder_mead(3, 20, |point| {
+            count += 1;
+            Ok(point.iter().map(|x| x * x).sum())
+        }).unwrap();
+
+        // The step that is started when the limit is reached is finished, which takes at most dimensions + 1 evaluations
+        assert!((20..=24).contains(&count));
+    }
+}
@@ -0,0 +1,546 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::path::Path;
+use error::Error;
+use error::Problem;
+use transport_mode::TransportMode;
+use journey_type::JourneyType;
+use initial_mode::InitialModeProbabilities;
+use initial_mode::Ownership;
+use parameters::Parameters;
+use scenario::Scenario;
+use intervention::Target;
+use weather::WeatherModel;
+use weather::WeatherSource;
+use validation;
+use rainfall;
+use rng;
+use rng::Stream;
+use agent_generation;
+use read_network;
+
+/// Probabilities should sum to 1, to within this
+const PROBABILITY_TOLERANCE: f64 = 1e-3;
+
+/// This checks every configuration file, before a simulation is run
+/// * parameters_file: The path of the parameters file
+/// * scenario_file: The path of the scenario file
+/// * weather_file: The path of the weather model file
+/// * networks_directory: The directory of the social networks, if they should be checked
+/// * agents_directory: The directory of the agents, if they should be checked
+/// * Returns: Nothing if there are no problems, otherwise Error::Invalid with every problem found
+pub fn check_config(
+    parameters_file: &str,
+    scenario_file: &str,
+    weather_file: &str,
+    networks_directory: Option<&str>,
+    agents_directory: Option<&str>) -> Result<(), Error>
+{
+    let mut problems = Vec::new();
+
+    // Every file is loaded first, so that problems loading any of them are reported together
+    let parameters = load(parameters_file, Parameters::from_file, &mut problems);
+    let scenario = load(scenario_file, Scenario::from_file, &mut problems);
+    let weather_model = load(weather_file, WeatherModel::from_file, &mut problems);
+
+    if let Some(ref parameters) = parameters {
+        check_parameters(parameters, parameters_file, &mut problems);
+    }
+
+    if let Some(ref scenario) = scenario {
+        check_scenario(scenario, parameters.as_ref(), scenario_file, &mut problems);
+    }
+
+    if let Some(ref weather_model) = weather_model {
+        let days = parameters.as_ref().map(|parameters| (365 * parameters.total_years) as usize);
+        check_weather_model(weather_model, days, weather_file, &mut problems);
+    }
+
+    if let (Some(ref parameters), Some(networks_directory)) = (&parameters, networks_directory) {
+        check_networks(parameters, networks_directory, &mut problems);
+    }
+
+    if let (Some(ref parameters), Some(ref scenario), Some(agents_directory)) = (&parameters, &scenario, agents_directory) {
+        check_agents(parameters, scenario, agents_directory, networks_directory, &mut problems);
+    }
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::Invalid(problems))
+    }
+}
+
+/// Loads a configuration file, adding a problem if it cannot be loaded
+/// * path: The path of the file
+/// * from_file: Loads the configuration from the file
+/// * problems: The problems found so far
+/// * Returns: The configuration, or None if it could not be loaded
+fn load<T>(path: &str, from_file: fn(File) -> Result<T, Error>, problems: &mut Vec<Problem>) -> Option<T> {
+    let result = File::open(path)
+        .map_err(Error::from)
+        .and_then(from_file);
+
+    match result {
+        Ok(config) => Some(config),
+        Err(error) => {
+            problems.push(Problem::new(path, "", error.to_string()));
+            None
+        }
+    }
+}
+
+/// Checks the parameters
+/// * parameters: The parameters to check
+/// * file: The file the parameters were loaded from
+/// * problems: The problems found so far, new problems are added to this
+fn check_parameters(parameters: &Parameters, file: &str, problems: &mut Vec<Problem>) {
+    if parameters.total_years == 0 {
+        problems.push(Problem::new(file, "total_years", "should be at least 1"));
+    }
+
+    if parameters.number_of_simulations == 0 {
+        problems.push(Problem::new(file, "number_of_simulations", "should be at least 1"));
+    }
+
+    if parameters.number_of_people <= parameters.number_of_social_network_links {
+        problems.push(Problem::new(file, "number_of_people",
+            format!("should be more than number_of_social_network_links ({})", parameters.number_of_social_network_links)));
+    }
+
+    let connectivities = [
+        ("social_connectivity", parameters.social_connectivity),
+        ("subculture_connectivity", parameters.subculture_connectivity),
+        ("neighbourhood_connectivity", parameters.neighbourhood_connectivity)
+    ];
+    for &(location, connectivity) in connectivities.iter() {
+        if !(connectivity >= 0.0 && connectivity.is_finite()) {
+            problems.push(Problem::new(file, location, format!("should be 0 or more, not {}", connectivity)));
+        }
+    }
+
+    if parameters.days_in_habit_average == 0 {
+        problems.push(Problem::new(file, "days_in_habit_average", "should be at least 1"));
+    }
+
+    // The distributions are (mean, sd, weight)
+    if parameters.distributions.is_empty() {
+        problems.push(Problem::new(file, "distributions", "at least one distribution is needed"));
+    }
+
+    for (i, &(mean, sd, weight)) in parameters.distributions.iter().enumerate() {
+        let location = format!("distributions[{}]", i);
+        if !mean.is_finite() {
+            problems.push(Problem::new(file, location.as_str(), format!("the mean should be a number, not {}", mean)));
+        }
+        if !(sd >= 0.0 && sd.is_finite()) {
+            problems.push(Problem::new(file, location.as_str(), format!("the sd should be 0 or more, not {}", sd)));
+        }
+        if !(weight >= 0.0 && weight.is_finite()) {
+            problems.push(Problem::new(file, location.as_str(), format!("the weight should be 0 or more, not {}", weight)));
+        }
+    }
+
+    let total_weight: f64 = parameters.distributions.iter().map(|&(_, _, weight)| weight).sum();
+    if !parameters.distributions.is_empty() && total_weight <= 0.0 {
+        problems.push(Problem::new(file, "distributions", "the weights should not all be 0"));
+    }
+
+    let (city_threshold, distant_threshold) = parameters.commute_length_thresholds;
+    if !(city_threshold > 0.0 && city_threshold < distant_threshold && distant_threshold.is_finite()) {
+        problems.push(Problem::new(file, "commute_length_thresholds",
+            format!("should be two increasing distances above 0, not ({}, {})", city_threshold, distant_threshold)));
+    }
+
+    if let Some(ref distance_decay) = parameters.distance_decay {
+        for mode in TransportMode::all().iter().filter(|mode| distance_decay.get(mode).is_some_and(|&d| d <= 0.0)) {
+            problems.push(Problem::new(file, format!("distance_decay.{:?}", mode), "should be above 0"));
+        }
+    }
+}
+
+/// Checks the scenario
+/// * scenario: The scenario to check
+/// * parameters: The parameters, if they were loaded, to check the scenario against
+/// * file: The file the scenario was loaded from
+/// * problems: The problems found so far, new problems are added to this
+fn check_scenario(scenario: &Scenario, parameters: Option<&Parameters>, file: &str, problems: &mut Vec<Problem>) {
+    if scenario.subcultures.is_empty() {
+        problems.push(Problem::new(file, "subcultures", "at least one subculture is needed"));
+    }
+
+    if scenario.neighbourhoods.is_empty() {
+        problems.push(Problem::new(file, "neighbourhoods", "at least one neighbourhood is needed"));
+    }
+
+    let subculture_ids = check_ids(scenario.subcultures.iter().map(|subculture| &subculture.id), "subcultures", file, problems);
+    let neighbourhood_ids = check_ids(scenario.neighbourhoods.iter().map(|neighbourhood| &neighbourhood.id), "neighbourhoods", file, problems);
+
+    for (i, subculture) in scenario.subcultures.iter().enumerate() {
+        let location = format!("subcultures[{}].desirability", i);
+        check_modes(&subculture.desirability.borrow(), &location, file, problems);
+        check_scores(&subculture.desirability.borrow(), &location, file, problems);
+    }
+
+    for (i, neighbourhood) in scenario.neighbourhoods.iter().enumerate() {
+        let location = format!("neighbourhoods[{}].supportiveness", i);
+        check_modes(&neighbourhood.supportiveness.borrow(), &location, file, problems);
+        check_scores(&neighbourhood.supportiveness.borrow(), &location, file, problems);
+        check_modes(&neighbourhood.capacity.borrow(), &format!("neighbourhoods[{}].capacity", i), file, problems);
+    }
+
+    for commute_length in JourneyType::all().iter() {
+        if let Some(probabilities) = scenario.initial_modes.get(commute_length) {
+            check_initial_modes(probabilities, &format!("initial_modes.{:?}", commute_length), file, problems);
+        }
+        for (i, neighbourhood) in scenario.neighbourhoods.iter().enumerate() {
+            if let Some(probabilities) = neighbourhood.initial_modes.get(commute_length) {
+                check_initial_modes(probabilities, &format!("neighbourhoods[{}].initial_modes.{:?}", i, commute_length), file, problems);
+            }
+        }
+    }
+
+    if let Some(parameters) = parameters {
+        if scenario.number_of_bikes > parameters.number_of_people {
+            problems.push(Problem::new(file, "number_of_bikes",
+                format!("there are more bikes than people ({})", parameters.number_of_people)));
+        }
+        if scenario.number_of_cars > parameters.number_of_people {
+            problems.push(Problem::new(file, "number_of_cars",
+                format!("there are more cars than people ({})", parameters.number_of_people)));
+        }
+    }
+
+    for &(location, costs) in [("costs.capacity", &scenario.costs.capacity), ("costs.supportiveness", &scenario.costs.supportiveness)].iter() {
+        for mode in TransportMode::all().iter().filter(|mode| costs.get(mode).is_some_and(|&cost| cost < 0.0)) {
+            problems.push(Problem::new(file, location, format!("the cost of {:?} should not be negative", mode)));
+        }
+    }
+    for &(location, cost) in [("costs.bike", scenario.costs.bike), ("costs.car", scenario.costs.car)].iter() {
+        if cost < 0.0 {
+            problems.push(Problem::new(file, location, "should not be negative"));
+        }
+    }
+
+    let problems_before_interventions = problems.len();
+
+    for (i, intervention) in scenario.interventions.iter().enumerate() {
+        let location = format!("interventions[{}]", i);
+
+        if intervention.start_day == 0 {
+            problems.push(Problem::new(file, format!("{}.start_day", location), "interventions start on day 1 or later"));
+        }
+
+        if let Some(parameters) = parameters {
+            if intervention.start_day >= parameters.total_years * 365 {
+                problems.push(Problem::new(file, format!("{}.start_day", location),
+                    format!("the simulation ends before day {}", intervention.start_day)));
+            }
+        }
+
+        if let Some(end_day) = intervention.end_day {
+            if end_day <= intervention.start_day {
+                problems.push(Problem::new(file, format!("{}.end_day", location), "should be after start_day"));
+            }
+        }
+
+        for (j, change) in intervention.neighbourhood_changes.iter().enumerate() {
+            if !neighbourhood_ids.contains(&change.id) {
+                problems.push(Problem::new(file, format!("{}.neighbourhood_changes[{}].id", location, j),
+                    format!("there is no neighbourhood {}", change.id)));
+            }
+            if change.ramp.as_ref().is_some_and(|ramp| ramp.days == 0) {
+                problems.push(Problem::new(file, format!("{}.neighbourhood_changes[{}].ramp.days", location, j),
+                    "should be at least 1"));
+            }
+        }
+
+        for (j, change) in intervention.subculture_changes.iter().enumerate() {
+            if !subculture_ids.contains(&change.id) {
+                problems.push(Problem::new(file, format!("{}.subculture_changes[{}].id", location, j),
+                    format!("there is no subculture {}", change.id)));
+            }
+        }
+
+        check_target(&intervention.bike_target, &neighbourhood_ids, &format!("{}.bike_target", location), file, problems);
+        check_target(&intervention.car_target, &neighbourhood_ids, &format!("{}.car_target", location), file, problems);
+    }
+
+    // The intervention schedule can only be simulated if the interventions are valid
+    if problems.len() == problems_before_interventions {
+        problems.extend(validation::check_interventions(scenario, file));
+    }
+}
+
+/// Checks a list of ids are unique
+/// * ids: The ids to check
+/// * location: The location of the list
+/// * file: The file the ids were loaded from
+/// * problems: The problems found so far, new problems are added to this
+/// * Returns: The ids
+fn check_ids<'a, I: Iterator<Item = &'a String>>(ids: I, location: &str, file: &str, problems: &mut Vec<Problem>) -> HashSet<String> {
+    let mut unique_ids = HashSet::new();
+    for (i, id) in ids.enumerate() {
+        if !unique_ids.insert(id.clone()) {
+            problems.push(Problem::new(file, format!("{}[{}].id", location, i), format!("{} is used more than once", id)));
+        }
+    }
+    unique_ids
+}
+
+/// Checks that every transport mode has a value
+/// * values: The value of each mode
+/// * location: The location of the values
+/// * file: The file the values were loaded from
+/// * problems: The problems found so far, new problems are added to this
+fn check_modes<T>(values: &HashMap<TransportMode, T>, location: &str, file: &str, problems: &mut Vec<Problem>) {
+    for mode in TransportMode::all().iter().filter(|mode| !values.contains_key(mode)) {
+        problems.push(Problem::new(file, location, format!("{:?} is missing", mode)));
+    }
+}
+
+/// Checks that every score is from 0-1
+/// * scores: The score of each mode
+/// * location: The location of the scores
+/// * file: The file the scores were loaded from
+/// * problems: The problems found so far, new problems are added to this
+fn check_scores(scores: &HashMap<TransportMode, f32>, location: &str, file: &str, problems: &mut Vec<Problem>) {
+    for mode in TransportMode::all().iter() {
+        if let Some(score) = scores.get(mode) {
+            if !(0.0..=1.0).contains(score) {
+                problems.push(Problem::new(file, format!("{}.{:?}", location, mode), format!("should be from 0-1, not {}", score)));
+            }
+        }
+    }
+}
+
+/// Checks initial mode probabilities, every combination of car and bike ownership needs a mode that can be chosen
+/// * probabilities: The probabilities to check
+/// * location: The location of the probabilities
+/// * file: The file the probabilities were loaded from
+/// * problems: The problems found so far, new problems are added to this
+fn check_initial_modes(probabilities: &InitialModeProbabilities, location: &str, file: &str, problems: &mut Vec<Problem>) {
+    for &ownership in Ownership::all().iter() {
+        let modes = probabilities.for_ownership(ownership);
+        for mode in TransportMode::all().iter().filter(|mode| modes.get(mode).is_some_and(|&p| p < 0.0)) {
+            problems.push(Problem::new(file, format!("{}.{}.{:?}", location, ownership.name(), mode), "should not be negative"));
+        }
+        for mode in TransportMode::all().iter().filter(|&&mode| modes.contains_key(&mode) && !ownership.can_use(mode)) {
+            problems.push(Problem::new(file, format!("{}.{}.{:?}", location, ownership.name(), mode),
+                format!("cannot be used by agents with the ownership {}", ownership.name())));
+        }
+        if modes.values().all(|&p| p <= 0.0) {
+            problems.push(Problem::new(file, format!("{}.{}", location, ownership.name()), "at least one mode should have a probability above 0"));
+        }
+    }
+
+    for mode in TransportMode::all().iter().filter(|mode| probabilities.half_distances.get(mode).is_some_and(|&d| d <= 0.0)) {
+        problems.push(Problem::new(file, format!("{}.half_distances.{:?}", location, mode), "should be above 0"));
+    }
+}
+
+/// Checks the neighbourhoods of a target exist
+/// * target: The target to check
+/// * neighbourhood_ids: The ids of the neighbourhoods in the scenario
+/// * location: The location of the target
+/// * file: The file the target was loaded from
+/// * problems: The problems found so far, new problems are added to this
+fn check_target(target: &Target, neighbourhood_ids: &HashSet<String>, location: &str, file: &str, problems: &mut Vec<Problem>) {
+    for (i, id) in target.neighbourhoods.iter().enumerate() {
+        if !neighbourhood_ids.contains(id) {
+            problems.push(Problem::new(file, format!("{}.neighbourhoods[{}]", location, i),
+                format!("there is no neighbourhood {}", id)));
+        }
+    }
+}
+
+/// Checks the weather model
+/// * weather_model: The weather model to check
+/// * file: The file the weather model was loaded from
+/// * problems: The problems found so far, new problems are added to this
+fn check_weather_model(weather_model: &WeatherModel, days: Option<usize>, file: &str, problems: &mut Vec<Problem>) {
+    if weather_model.states.is_empty() {
+        problems.push(Problem::new(file, "states", "at least one state is needed"));
+    }
+
+    let state_ids = check_ids(weather_model.states.iter().map(|state| &state.id), "states", file, problems);
+
+    for (i, state) in weather_model.states.iter().enumerate() {
+        check_scores(&state.discouragement, &format!("states[{}].discouragement", i), file, problems);
+    }
+
+    match weather_model.source {
+        WeatherSource::MarkovChain => {
+            check_probabilities(&weather_model.initial_probabilities, &state_ids, "initial_probabilities", file, problems);
+
+            for id in weather_model.states.iter().map(|state| &state.id) {
+                match weather_model.transition_matrix.get(id) {
+                    Some(transitions) => check_probabilities(
+                        transitions, &state_ids, &format!("transition_matrix.{}", id), file, problems),
+                    None => problems.push(Problem::new(file, "transition_matrix", format!("{} is missing", id)))
+                }
+            }
+
+            for (from, transition_matrix) in weather_model.second_order_transition_matrix.iter() {
+                for (to, transitions) in transition_matrix.iter() {
+                    check_probabilities(transitions, &state_ids,
+                        &format!("second_order_transition_matrix.{}.{}", from, to), file, problems);
+                }
+            }
+        },
+        WeatherSource::Historical { file: ref rainfall_file, ref start_date } => {
+            let problems_before = problems.len();
+
+            if !Path::new(rainfall_file).is_file() {
+                problems.push(Problem::new(file, "source.Historical.file", format!("{} cannot be found", rainfall_file)));
+            }
+
+            if rainfall::day_number(start_date).is_none() {
+                problems.push(Problem::new(file, "source.Historical.start_date", format!("{} is not a valid date", start_date)));
+            }
+
+            // Every day's rainfall is classified, so a state is needed for days without rain
+            if weather_model.states.iter().all(|state| state.minimum_rainfall.is_none_or(|minimum| minimum > 0.0)) {
+                problems.push(Problem::new(file, "states", "a state with a minimum_rainfall of 0 is needed to replay rainfall"));
+            }
+
+            // Replaying the rainfall checks every row, and that every date replayed has a row,
+            // when the number of days is not known only the rows are checked
+            if problems.len() == problems_before {
+                let result = match days {
+                    // The random number generator is not used to replay rainfall
+                    Some(days) => weather_model.make_pattern(days, &mut rng::create_rng(0, 0, Stream::Weather)).map(|_| ()),
+                    None => rainfall::read_rainfall(rainfall_file).map(|_| ())
+                };
+
+                match result {
+                    Ok(()) => {},
+                    Err(Error::Invalid(rainfall_problems)) => problems.extend(rainfall_problems),
+                    Err(error) => problems.push(Problem::new(rainfall_file.as_str(), "", error.to_string()))
+                }
+            }
+        }
+    }
+}
+
+/// Checks the chance of moving to each weather state, the states should exist and the chances should sum to 1
+/// * probabilities: The chance of each state, by id
+/// * state_ids: The ids of the states in the weather model
+/// * location: The location of the probabilities
+/// * file: The file the probabilities were loaded from
+/// * problems: The problems found so far, new problems are added to this
+fn check_probabilities(
+    probabilities: &HashMap<String, f64>,
+    state_ids: &HashSet<String>,
+    location: &str,
+    file: &str,
+    problems: &mut Vec<Problem>)
+{
+    let mut ids: Vec<&String> = probabilities.keys().collect();
+    ids.sort();
+    for id in ids {
+        if !state_ids.contains(id) {
+            problems.push(Problem::new(file, location, format!("there is no state {}", id)));
+        }
+        if probabilities[id] < 0.0 {
+            problems.push(Problem::new(file, format!("{}.{}", location, id), "should be 0 or more"));
+        }
+    }
+
+    let total: f64 = probabilities.values().sum();
+    if (total - 1.0).abs() > PROBABILITY_TOLERANCE {
+        problems.push(Problem::new(file, location, format!("the probabilities should sum to 1, not {}", total)));
+    }
+}
+
+/// Checks there is a social network for each simulation, of the right size
+/// * parameters: The parameters of the simulation
+/// * networks_directory: The directory of the social networks
+/// * problems: The problems found so far, new problems are added to this
+fn check_networks(parameters: &Parameters, networks_directory: &str, problems: &mut Vec<Problem>) {
+    for id in 1..=parameters.number_of_simulations {
+        let file = format!("{}/{}.yaml", networks_directory, id);
+        let network = match load(&file, read_network, problems) {
+            Some(network) => network,
+            None => continue
+        };
+
+        if network.len() != parameters.number_of_people as usize {
+            problems.push(Problem::new(file.as_str(), "",
+                format!("the network has {} people, but number_of_people is {}", network.len(), parameters.number_of_people)));
+        }
+
+        let mut outside: Vec<u32> = network
+            .iter()
+            .flat_map(|(id, friends)| ::std::iter::once(id).chain(friends.iter()))
+            .filter(|&&id| id >= parameters.number_of_people)
+            .cloned()
+            .collect();
+        outside.sort();
+        outside.dedup();
+        for id in outside {
+            problems.push(Problem::new(file.as_str(), "",
+                format!("there is no person {}, there are {} people", id, parameters.number_of_people)));
+        }
+    }
+}
+
+/// Checks the agents of each simulation can be loaded, and live in neighbourhoods and belong to subcultures of the scenario
+/// * parameters: The parameters of the simulation
+/// * scenario: The scenario the agents are loaded into
+/// * agents_directory: The directory of the agents
+/// * networks_directory: The directory of the social networks, if every person in them should have an agent
+/// * problems: The problems found so far, new problems are added to this
+fn check_agents(
+    parameters: &Parameters,
+    scenario: &Scenario,
+    agents_directory: &str,
+    networks_directory: Option<&str>,
+    problems: &mut Vec<Problem>)
+{
+    for id in 1..=parameters.number_of_simulations {
+        let file = format!("{}/{}.yaml", agents_directory, id);
+        let result = File::open(&file)
+            .map_err(Error::from)
+            .and_then(|agents| agent_generation::load_unlinked_agents_from_file(
+                agents, &file, &scenario.neighbourhoods, &scenario.subcultures));
+
+        let agents = match result {
+            Ok(agents) => agents,
+            Err(Error::Invalid(agent_problems)) => {
+                problems.extend(agent_problems);
+                continue;
+            },
+            Err(error) => {
+                problems.push(Problem::new(file.as_str(), "", error.to_string()));
+                continue;
+            }
+        };
+
+        if agents.len() != parameters.number_of_people as usize {
+            problems.push(Problem::new(file.as_str(), "",
+                format!("there are {} agents, but number_of_people is {}", agents.len(), parameters.number_of_people)));
+        }
+
+        // Problems loading the network are reported by check_networks
+        let network = networks_directory.and_then(|networks_directory| File::open(format!("{}/{}.yaml", networks_directory, id))
+            .map_err(Error::from)
+            .and_then(read_network)
+            .ok());
+        if let Some(network) = network {
+            let without_agents: BTreeSet<u32> = network
+                .iter()
+                .flat_map(|(id, friends)| ::std::iter::once(id).chain(friends.iter()))
+                .filter(|&&id| id as usize >= agents.len())
+                .cloned()
+                .collect();
+            if let Some(first) = without_agents.iter().next() {
+                problems.push(Problem::new(file.as_str(), "", format!(
+                    "{} people in the social network have no agent, the first is person {}, there are {} agents",
+                    without_agents.len(), first, agents.len())));
+            }
+        }
+    }
+}
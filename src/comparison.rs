@@ -0,0 +1,77 @@
+use std::collections::BTreeMap;
+use error::Error;
+use error::Problem;
+use summary;
+
+/// The final year active mode share of each replicate of a scenario
+/// * output_directory: The directory containing output_n.csv files
+/// * number_of_people: The number of people in each simulation
+/// * Returns: The share of each replicate, by simulation ID
+pub fn final_year_active_shares(output_directory: &str, number_of_people: u32) -> Result<BTreeMap<u32, f64>, Error> {
+    summary::find_outputs(output_directory)?
+        .iter()
+        .map(|&(id, ref path)| Ok((id, summary::final_year_active_share(path, number_of_people)?)))
+        .collect()
+}
+
+/// The mean and sample standard deviation of some values
+/// * values: The values
+/// * Returns: (mean, sd), the sd is 0 if there are fewer than 2 values
+pub fn mean_and_sd(values: &[f64]) -> (f64, f64) {
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    let sd = if values.len() > 1 {
+        (values.iter().map(|value| (value - mean).powi(2)).sum::<f64>() / (n - 1.0)).sqrt()
+    } else {
+        0.0
+    };
+    (mean, sd)
+}
+
+/// Compares the final year active mode share of each scenario to a baseline, replicate by replicate  
+/// Replicate n of every scenario used the same network, agents and random numbers, so the
+/// difference between them is caused by the scenario
+/// * baseline: The ID and output directory of the baseline scenario
+/// * scenarios: The ID and output directory of each scenario
+/// * number_of_people: The number of people in each simulation
+/// * Returns: A CSV of the difference of each replicate, and a CSV of the mean difference of each scenario
+pub fn compare_to_baseline(
+    baseline: &(String, String),
+    scenarios: &[(String, String)],
+    number_of_people: u32) -> Result<(String, String), Error>
+{
+    let baseline_shares = final_year_active_shares(&baseline.1, number_of_people)?;
+
+    let mut differences = String::from("Scenario,Replicate,BaselineActiveShare,ActiveShare,Difference\n");
+    let mut summary = String::from("Scenario,Replicates,MeanDifference,SdDifference,StandardError\n");
+    let mut problems = Vec::new();
+
+    for (id, output_directory) in scenarios.iter() {
+        let shares = final_year_active_shares(output_directory, number_of_people)?;
+
+        let mut scenario_differences = Vec::new();
+        for (replicate, share) in shares.iter() {
+            match baseline_shares.get(replicate) {
+                Some(baseline_share) => {
+                    differences.push_str(&format!("{},{},{},{},{}\n",
+                        id, replicate, baseline_share, share, share - baseline_share));
+                    scenario_differences.push(share - baseline_share);
+                },
+                None => problems.push(Problem::new(output_directory.as_str(), "",
+                    format!("replicate {} has no baseline replicate", replicate)))
+            }
+        }
+
+        if !scenario_differences.is_empty() {
+            let (mean, sd) = mean_and_sd(&scenario_differences);
+            summary.push_str(&format!("{},{},{},{},{}\n",
+                id, scenario_differences.len(), mean, sd, sd / (scenario_differences.len() as f64).sqrt()));
+        }
+    }
+
+    if problems.is_empty() {
+        Ok((differences, summary))
+    } else {
+        Err(Error::Invalid(problems))
+    }
+}
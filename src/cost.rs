@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+use transport_mode::TransportMode;
+use intervention::Intervention;
+use intervention::NeighbourhoodChange;
+
+/// The cost of each unit of change an intervention can make, changes without a cost are free
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Costs {
+    /// The cost of adding, or removing, one unit of capacity for each TransportMode
+    #[serde(default)]
+    pub capacity: HashMap<TransportMode, f64>,
+
+    /// The cost of increasing, or decreasing, the supportiveness of a neighbourhood by 0.1 for each TransportMode
+    #[serde(default)]
+    pub supportiveness: HashMap<TransportMode, f64>,
+
+    /// The cost of giving, or taking away, a bike
+    #[serde(default)]
+    pub bike: f64,
+
+    /// The cost of giving, or taking away, a car
+    #[serde(default)]
+    pub car: f64
+}
+
+impl Costs {
+    /// The cost of a change to a neighbourhood
+    /// * change: The change
+    /// * Returns: The cost of the whole change in supportiveness and capacity
+    pub fn of_neighbourhood_change(&self, change: &NeighbourhoodChange) -> f64 {
+        // The number of 0.1 steps is found before widening the f32, so 0.2 is exactly 2 steps
+        let supportiveness: f64 = change
+            .increase_in_supportiveness
+            .iter()
+            .map(|(mode, &v)| self.supportiveness.get(mode).unwrap_or(&0.0) * f64::from(v.abs() / 0.1))
+            .sum();
+
+        let capacity: f64 = change
+            .increase_in_capacity
+            .iter()
+            .map(|(mode, &v)| self.capacity.get(mode).unwrap_or(&0.0) * (v as f64).abs())
+            .sum();
+
+        supportiveness + capacity
+    }
+
+    /// The cost of an intervention, changes in desirability are not costed
+    /// * intervention: The intervention
+    /// * Returns: The cost of its neighbourhood changes, and the bikes and cars given or taken away
+    pub fn of_intervention(&self, intervention: &Intervention) -> f64 {
+        intervention
+            .neighbourhood_changes
+            .iter()
+            .map(|change| self.of_neighbourhood_change(change))
+            .sum::<f64>()
+            + self.bike * f64::from(intervention.change_in_number_of_bikes.unsigned_abs())
+            + self.car * f64::from(intervention.change_in_number_of_cars.unsigned_abs())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_yaml;
+
+    #[test]
+    fn of_intervention_sums_every_change() {
+        let costs = Costs {
+            capacity: hashmap! { TransportMode::Car => 10.0 },
+            supportiveness: hashmap! { TransportMode::Cycle => 1000.0 },
+            bike: 50.0,
+            car: 200.0
+        };
+        let intervention: Intervention = serde_yaml::from_str("
+            start_day: 0
+            neighbourhood_changes:
+              - id: \"1\"
+                increase_in_supportiveness: {Cycle: 0.2}
+                increase_in_capacity: {Car: -500, Walk: 1000}
+            change_in_number_of_bikes: 100
+            change_in_number_of_cars: -20").unwrap();
+
+        // 2 steps of supportiveness, 500 car capacity, 100 bikes and 20 cars, walking capacity is free
+        assert_eq!(costs.of_intervention(&intervention), 2000.0 + 5000.0 + 5000.0 + 4000.0);
+    }
+
+    #[test]
+    fn changes_without_a_cost_are_free() {
+        let intervention: Intervention = serde_yaml::from_str("
+            start_day: 0
+            neighbourhood_changes:
+              - id: \"1\"
+                increase_in_supportiveness: {Cycle: 0.3}
+            change_in_number_of_bikes: 100
+            change_in_number_of_cars: 0").unwrap();
+
+        assert_eq!(Costs::default().of_intervention(&intervention), 0.0);
+    }
+}
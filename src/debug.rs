@@ -14,9 +14,7 @@ pub fn print_transport_mode_hashmap(prefix: &str, hashmap: &HashMap<TransportMod
             TransportMode::Walk => format!("Walk: {}", value),
             TransportMode::PublicTransport => format!("PT: {}", value)
         })
-        .intersperse(",".to_string())
-        .collect::<Vec<_>>()
-        .concat();
+        .join(",");
     
     debug!("{}: {}", prefix, hashmap_str);
 }
\ No newline at end of file
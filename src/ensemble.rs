@@ -0,0 +1,149 @@
+use comparison;
+use comparison::mean_and_sd;
+use error::Error;
+use error::Problem;
+use summary;
+
+/// The two-sided 95% critical values of Student's t distribution, for 1 to 30 degrees of freedom
+const T_CRITICAL_VALUES: [f64; 30] = [
+    12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228,
+    2.201, 2.179, 2.160, 2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086,
+    2.080, 2.074, 2.069, 2.064, 2.060, 2.056, 2.052, 2.048, 2.045, 2.042
+];
+
+/// The percentiles of each column written to the ensemble summary
+const PERCENTILES: [f64; 3] = [5.0, 50.0, 95.0];
+
+/// The two-sided 95% critical value of Student's t distribution
+/// * degrees_of_freedom: The degrees of freedom, at least 1
+/// * Returns: The critical value, the normal approximation is used above 30 degrees of freedom
+pub fn t_critical_value(degrees_of_freedom: usize) -> f64 {
+    match degrees_of_freedom {
+        0 => f64::NAN,
+        1..=30 => T_CRITICAL_VALUES[degrees_of_freedom - 1],
+        _ => 1.96
+    }
+}
+
+/// A percentile of some values, interpolating linearly between the closest ranks
+/// * sorted_values: The values, in ascending order, there must be at least one
+/// * percentile: The percentile, from 0-100
+/// * Returns: The value of the percentile
+pub fn percentile(sorted_values: &[f64], percentile: f64) -> f64 {
+    let rank = percentile / 100.0 * (sorted_values.len() - 1) as f64;
+    let lower = rank.floor() as usize;
+    let upper = rank.ceil() as usize;
+    sorted_values[lower] + (sorted_values[upper] - sorted_values[lower]) * (rank - lower as f64)
+}
+
+/// Summarises every replicate of a scenario, day by day  
+/// Each replicate writes the same days, so row n of every output is the same day
+/// * output_directory: The directory containing output_n.csv files
+/// * Returns: A CSV with a row for each day, and the mean, sd and 5th, 50th and 95th percentiles of each column
+///   across the replicates
+pub fn summarise_days(output_directory: &str) -> Result<String, Error> {
+    let outputs = summary::find_outputs(output_directory)?;
+    if outputs.is_empty() {
+        return Err(Error::Invalid(vec![Problem::new(output_directory, "", "there are no outputs to summarise")]));
+    }
+
+    let mut replicates = Vec::new();
+    for (_, path) in outputs.iter() {
+        replicates.push((path, summary::read_output(path)?));
+    }
+
+    // Every replicate must have the same columns and days as the first
+    let (first_path, (ref header, ref first_rows)) = replicates[0];
+    let day_column = summary::find_column(header, "Day", first_path)?;
+    let mut problems = Vec::new();
+    for &(path, (ref replicate_header, ref rows)) in replicates.iter().skip(1) {
+        if replicate_header != header {
+            problems.push(Problem::new(path.as_str(), "", format!("the columns are not the same as {}", first_path)));
+        } else if rows.len() != first_rows.len()
+            || rows.iter().zip(first_rows.iter()).any(|(row, first_row)| row.get(day_column) != first_row.get(day_column)) {
+            problems.push(Problem::new(path.as_str(), "", format!("the days are not the same as {}", first_path)));
+        }
+    }
+    if !problems.is_empty() {
+        return Err(Error::Invalid(problems));
+    }
+
+    let columns: Vec<usize> = (0..header.len()).filter(|&column| column != day_column).collect();
+
+    let mut table = String::from("Day");
+    for &column in columns.iter() {
+        table.push_str(&format!(",{0}Mean,{0}Sd", header[column]));
+        for percentile in PERCENTILES.iter() {
+            table.push_str(&format!(",{}P{}", header[column], percentile));
+        }
+    }
+    table.push('\n');
+
+    for (row, first_row) in first_rows.iter().enumerate() {
+        table.push_str(&first_row[day_column]);
+        for &column in columns.iter() {
+            let mut values = Vec::with_capacity(replicates.len());
+            for &(path, (_, ref rows)) in replicates.iter() {
+                match rows[row].get(column).and_then(|value| value.parse::<f64>().ok()) {
+                    Some(value) => values.push(value),
+                    None => return Err(Error::Invalid(vec![Problem::new(
+                        path.as_str(),
+                        format!("day {}", first_row[day_column]),
+                        format!("{} is not a number", header[column]))]))
+                }
+            }
+            values.sort_by(|a, b| a.total_cmp(b));
+
+            let (mean, sd) = mean_and_sd(&values);
+            table.push_str(&format!(",{},{}", mean, sd));
+            for &p in PERCENTILES.iter() {
+                table.push_str(&format!(",{}", percentile(&values, p)));
+            }
+        }
+        table.push('\n');
+    }
+
+    Ok(table)
+}
+
+/// The 95% confidence interval of the mean final year active mode share of a scenario
+/// * output_directory: The directory containing output_n.csv files
+/// * number_of_people: The number of people in each simulation
+/// * Returns: A CSV of the number of replicates, and the mean, sd, standard error and confidence interval of their shares,
+///   the interval is not a number if there is only one replicate
+pub fn final_year_active_share_interval(output_directory: &str, number_of_people: u32) -> Result<String, Error> {
+    let shares = comparison::final_year_active_shares(output_directory, number_of_people)?;
+    if shares.is_empty() {
+        return Err(Error::Invalid(vec![Problem::new(output_directory, "", "there are no outputs to summarise")]));
+    }
+
+    let values: Vec<f64> = shares.values().cloned().collect();
+    let (mean, sd) = mean_and_sd(&values);
+    let standard_error = sd / (values.len() as f64).sqrt();
+    let half_width = t_critical_value(values.len() - 1) * standard_error;
+
+    Ok(format!("Replicates,Mean,Sd,StandardError,Lower95,Upper95\n{},{},{},{},{},{}\n",
+        values.len(), mean, sd, standard_error, mean - half_width, mean + half_width))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentile_interpolates_between_ranks() {
+        let values = [1.0, 2.0, 3.0, 4.0, 5.0];
+        assert_eq!(percentile(&values, 0.0), 1.0);
+        assert_eq!(percentile(&values, 25.0), 2.0);
+        assert_eq!(percentile(&values, 50.0), 3.0);
+        assert_eq!(percentile(&values, 100.0), 5.0);
+        assert!((percentile(&values, 90.0) - 4.6).abs() < 1e-12);
+    }
+
+    #[test]
+    fn percentile_of_one_value_is_the_value() {
+        for &p in [0.0, 5.0, 50.0, 95.0, 100.0].iter() {
+            assert_eq!(percentile(&[7.0], p), 7.0);
+        }
+    }
+}
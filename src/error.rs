@@ -0,0 +1,89 @@
+use std::error;
+use std::fmt;
+use std::io;
+use serde_yaml;
+
+/// An error loading or checking the configuration of the model
+#[derive(Debug)]
+pub enum Error {
+    /// A file could not be read or written
+    Io(io::Error),
+    /// A YAML file could not be parsed, the message includes the line and column
+    Yaml(serde_yaml::Error),
+    /// The configuration was parsed, but has problems
+    Invalid(Vec<Problem>)
+}
+
+/// A problem found when checking the configuration
+#[derive(Debug)]
+pub struct Problem {
+    /// The file the problem is in
+    pub file: String,
+    /// Where in the YAML file the problem is, e.g. neighbourhoods[2].capacity
+    pub location: String,
+    /// What the problem is
+    pub message: String
+}
+
+impl Problem {
+    /// Create a new problem
+    /// * file: The file the problem is in
+    /// * location: Where in the YAML file the problem is
+    /// * message: What the problem is
+    /// * Returns: The problem
+    pub fn new<F: Into<String>, L: Into<String>, M: Into<String>>(file: F, location: L, message: M) -> Self {
+        Problem {
+            file: file.into(),
+            location: location.into(),
+            message: message.into()
+        }
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.location.is_empty() {
+            write!(f, "{}: {}", self.file, self.message)
+        } else {
+            write!(f, "{}: {}: {}", self.file, self.location, self.message)
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Io(ref error) => write!(f, "{}", error),
+            Error::Yaml(ref error) => write!(f, "{}", error),
+            Error::Invalid(ref problems) => {
+                write!(f, "{} problem(s) were found", problems.len())?;
+                for problem in problems.iter() {
+                    write!(f, "\n{}", problem)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::Io(ref error) => Some(error),
+            Error::Yaml(ref error) => Some(error),
+            Error::Invalid(_) => None
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::Io(error)
+    }
+}
+
+impl From<serde_yaml::Error> for Error {
+    fn from(error: serde_yaml::Error) -> Self {
+        Error::Yaml(error)
+    }
+}
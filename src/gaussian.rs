@@ -27,15 +27,16 @@
 
 use rand::distributions::{Normal, Distribution};
 use rand::Rng;
-use rand::thread_rng;
 
 /// This gets samples from a Gaussian Mixture Model
 /// * count: The number of samples
 /// * distribution: A vec of tuples (mean, sd, weight)
+/// * rng: The random number generator
 /// * Returns: A vec of samples
-pub fn get_samples_from_gmm(
+pub fn get_samples_from_gmm<R: Rng>(
     count: usize,
-    distributions: Vec<(f64, f64, f64)>)
+    distributions: Vec<(f64, f64, f64)>,
+    rng: &mut R)
     -> Vec<f64>
 {
     // Create the Normal distributions
@@ -50,19 +51,16 @@ pub fn get_samples_from_gmm(
         .map(|(_, _, weight)| *weight)
         .collect();
 
-    // Create a random number generator    
-    let mut rng = thread_rng();
-
     // Create a vec for the samples
     let mut samples = Vec::with_capacity(count);
 
     // Generate count samples
     for _ in 0..count {
         // Chose a distribution based upon its weight
-        let chosen_guassian = gaussians[pick_distribution_id(&weights, &mut rng)];
+        let chosen_guassian = gaussians[pick_distribution_id(&weights, rng)];
 
         // Push a sample from that distribution to the samples vec
-        samples.push(chosen_guassian.sample(&mut rng));
+        samples.push(chosen_guassian.sample(rng));
 
     }
     // Return the generated samples
@@ -71,7 +69,7 @@ pub fn get_samples_from_gmm(
 
 /// Pick a distribution id to draw from
 /// * weights: The weights of each distribution, where the index in weights corresponds
-/// to the index of a distribution in another vec
+///   to the index of a distribution in another vec
 fn pick_distribution_id<R: Rng>(weights: &[f64], rng: &mut R) -> usize {
     // Calculate the total of all the weights
     let total_weight = weights
@@ -0,0 +1,226 @@
+use std::collections::HashMap;
+use rand::Rng;
+use transport_mode::TransportMode;
+use journey_type::JourneyType;
+use neighbourhood::Neighbourhood;
+use scenario::Scenario;
+
+/// The probability of an agent starting with each mode, depending on whether they own a car and a bike,
+/// and how far they commute  
+/// The probabilities of each combination are scaled to sum to 1, modes that are left out are never chosen
+#[derive(Clone, Serialize, Deserialize)]
+pub struct InitialModeProbabilities {
+    /// Agents who own a car and a bike
+    #[serde(default = "default_car_and_bike")]
+    pub car_and_bike: HashMap<TransportMode, f64>,
+
+    /// Agents who own a car, but not a bike
+    #[serde(default = "default_car")]
+    pub car: HashMap<TransportMode, f64>,
+
+    /// Agents who own a bike, but not a car
+    #[serde(default = "default_bike")]
+    pub bike: HashMap<TransportMode, f64>,
+
+    /// Agents who own neither a car nor a bike
+    #[serde(default = "default_neither")]
+    pub neither: HashMap<TransportMode, f64>,
+
+    /// The commute distance (in metres) at which the probability of a mode halves, and halves again at twice the distance,
+    /// the probabilities of modes that are left out do not depend on the distance
+    #[serde(default = "default_half_distances")]
+    pub half_distances: HashMap<TransportMode, f64>
+}
+
+/// The default probabilities for agents who own a car and a bike
+fn default_car_and_bike() -> HashMap<TransportMode, f64> {
+    hashmap! {
+        TransportMode::Car => 0.4,
+        TransportMode::Cycle => 0.3,
+        TransportMode::Walk => 0.15,
+        TransportMode::PublicTransport => 0.15
+    }
+}
+
+/// The default probabilities for agents who own a car, but not a bike
+fn default_car() -> HashMap<TransportMode, f64> {
+    hashmap! {
+        TransportMode::Car => 0.57,
+        TransportMode::Walk => 0.22,
+        TransportMode::PublicTransport => 0.21
+    }
+}
+
+/// The default probabilities for agents who own a bike, but not a car
+fn default_bike() -> HashMap<TransportMode, f64> {
+    hashmap! {
+        TransportMode::Cycle => 0.5,
+        TransportMode::Walk => 0.25,
+        TransportMode::PublicTransport => 0.25
+    }
+}
+
+/// The default probabilities for agents who own neither a car nor a bike
+fn default_neither() -> HashMap<TransportMode, f64> {
+    hashmap! {
+        TransportMode::Walk => 0.5,
+        TransportMode::PublicTransport => 0.5
+    }
+}
+
+/// The default distances at which the probabilities of the active modes halve
+fn default_half_distances() -> HashMap<TransportMode, f64> {
+    hashmap! {
+        TransportMode::Walk => 2000.0,
+        TransportMode::Cycle => 8000.0
+    }
+}
+
+impl Default for InitialModeProbabilities {
+    fn default() -> Self {
+        InitialModeProbabilities {
+            car_and_bike: default_car_and_bike(),
+            car: default_car(),
+            bike: default_bike(),
+            neither: default_neither(),
+            half_distances: default_half_distances()
+        }
+    }
+}
+
+impl InitialModeProbabilities {
+    /// The probabilities for agents with an ownership
+    /// * ownership: Whether the agents own a car and a bike
+    /// * Returns: The probability of each mode
+    pub fn for_ownership(&self, ownership: Ownership) -> &HashMap<TransportMode, f64> {
+        match ownership {
+            Ownership::CarAndBike => &self.car_and_bike,
+            Ownership::Car => &self.car,
+            Ownership::Bike => &self.bike,
+            Ownership::Neither => &self.neither
+        }
+    }
+
+    /// The probabilities for an agent, after they decay with the commute distance
+    /// * ownership: Whether the agent owns a car and a bike
+    /// * distance: The commute distance of the agent in metres
+    /// * Returns: The probability of each mode, which need not sum to 1
+    pub fn at_distance(&self, ownership: Ownership, distance: f64) -> HashMap<TransportMode, f64> {
+        self.for_ownership(ownership)
+            .iter()
+            .map(|(&mode, &probability)| match self.half_distances.get(&mode) {
+                Some(half_distance) => (mode, probability * 0.5f64.powf(distance / half_distance)),
+                None => (mode, probability)
+            })
+            .collect()
+    }
+
+    /// The probabilities for agents with an ownership, so they can be changed
+    /// * ownership: Whether the agents own a car and a bike
+    /// * Returns: The probability of each mode
+    pub fn for_ownership_mut(&mut self, ownership: Ownership) -> &mut HashMap<TransportMode, f64> {
+        match ownership {
+            Ownership::CarAndBike => &mut self.car_and_bike,
+            Ownership::Car => &mut self.car,
+            Ownership::Bike => &mut self.bike,
+            Ownership::Neither => &mut self.neither
+        }
+    }
+}
+
+/// Whether an agent owns a car and a bike, named as in the initial mode probabilities
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Ownership {
+    CarAndBike,
+    Car,
+    Bike,
+    Neither
+}
+
+impl Ownership {
+    /// The ownership of an agent
+    /// * owns_car: Whether the agent owns a car
+    /// * owns_bike: Whether the agent owns a bike
+    /// * Returns: The ownership
+    pub fn of(owns_car: bool, owns_bike: bool) -> Ownership {
+        match (owns_car, owns_bike) {
+            (true, true) => Ownership::CarAndBike,
+            (true, false) => Ownership::Car,
+            (false, true) => Ownership::Bike,
+            (false, false) => Ownership::Neither
+        }
+    }
+
+    /// Every ownership, in the order of the initial mode probabilities
+    pub fn all() -> [Ownership; 4] {
+        [Ownership::CarAndBike, Ownership::Car, Ownership::Bike, Ownership::Neither]
+    }
+
+    /// Whether agents with the ownership can use a mode, a car or a bike is needed to drive or cycle
+    /// * mode: The transport mode
+    /// * Returns: true if the mode can be used
+    pub fn can_use(self, mode: TransportMode) -> bool {
+        match mode {
+            TransportMode::Car => self == Ownership::CarAndBike || self == Ownership::Car,
+            TransportMode::Cycle => self == Ownership::CarAndBike || self == Ownership::Bike,
+            TransportMode::Walk | TransportMode::PublicTransport => true
+        }
+    }
+
+    /// The name of the ownership, as in the initial mode probabilities
+    pub fn name(self) -> &'static str {
+        match self {
+            Ownership::CarAndBike => "car_and_bike",
+            Ownership::Car => "car",
+            Ownership::Bike => "bike",
+            Ownership::Neither => "neither"
+        }
+    }
+}
+
+/// The initial mode probabilities of an agent  
+/// Those of the neighbourhood are used if it has them for the commute length, then those of the scenario,
+/// otherwise the defaults
+/// * scenario: The scenario of the simulation
+/// * neighbourhood: The neighbourhood the agent lives in
+/// * commute_length: The commute length of the agent
+/// * Returns: The probabilities
+pub fn probabilities_for(
+    scenario: &Scenario,
+    neighbourhood: &Neighbourhood,
+    commute_length: JourneyType) -> InitialModeProbabilities
+{
+    neighbourhood.initial_modes
+        .get(&commute_length)
+        .or_else(|| scenario.initial_modes.get(&commute_length))
+        .cloned()
+        .unwrap_or_default()
+}
+
+/// Choose an initial mode
+/// * probabilities: The probability of each mode, which need not sum to 1
+/// * rng: The random number generator
+/// * Returns: The chosen transport mode
+pub fn choose<R: Rng>(probabilities: &HashMap<TransportMode, f64>, rng: &mut R) -> TransportMode {
+    // The modes in the order they are drawn
+    let modes = [TransportMode::Car, TransportMode::Cycle, TransportMode::Walk, TransportMode::PublicTransport];
+    let total: f64 = modes.iter().map(|mode| probabilities.get(mode).unwrap_or(&0.0)).sum();
+
+    let randfloat = rng.gen::<f64>() * total;
+    let mut cumulative = 0.0;
+    for mode in modes.iter().filter(|mode| probabilities.get(mode).is_some_and(|&p| p > 0.0)) {
+        cumulative += probabilities[mode];
+        if randfloat < cumulative {
+            return *mode;
+        }
+    }
+
+    // Rounding can leave the draw at the total, which belongs to the last mode that can be chosen
+    modes
+        .iter()
+        .rev()
+        .find(|mode| probabilities.get(mode).is_some_and(|&p| p > 0.0))
+        .cloned()
+        .unwrap_or(TransportMode::PublicTransport)
+}
@@ -1,20 +1,83 @@
 use transport_mode::TransportMode;
+use journey_type::JourneyType;
+use agent::Agent;
 use std::collections::HashMap;
 
 /// This defined an intervention that can take place
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Intervention {
-    /// The day number of the intervention
-    pub day: u32,
+    /// The day number the intervention starts
+    #[serde(alias = "day")]
+    pub start_day: u32,
+
+    /// The day number the intervention ends, when its changes are reverted,
+    /// if this is not given the intervention is permanent
+    #[serde(default)]
+    pub end_day: Option<u32>,
     
     /// Changes in the neighbourhood
     pub neighbourhood_changes: Vec<NeighbourhoodChange>,
 
+    /// Changes in the subculture
+    #[serde(default)]
+    pub subculture_changes: Vec<SubcultureChange>,
+
     /// Change in the number of bikes
     pub change_in_number_of_bikes: i32,
 
     /// Change in the number of cars
     pub change_in_number_of_cars: i32,
+
+    /// The agents who may be given (or lose) bikes, if this is not given any agent may be chosen
+    #[serde(default)]
+    pub bike_target: Target,
+
+    /// The agents who may be given (or lose) cars, if this is not given any agent may be chosen
+    #[serde(default)]
+    pub car_target: Target,
+}
+
+/// This defines which agents a change in the number of bikes or cars is targeted at,
+/// an agent must meet every criterion that is given
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Target {
+    /// The IDs of the neighbourhoods the agents live in, if empty any neighbourhood
+    #[serde(default)]
+    pub neighbourhoods: Vec<String>,
+
+    /// The commute lengths of the agents, if empty any commute length
+    #[serde(default)]
+    pub commute_lengths: Vec<JourneyType>,
+
+    /// The modes the agents currently travel by, if empty any mode
+    #[serde(default)]
+    pub current_modes: Vec<TransportMode>,
+
+    /// How the agents are chosen from those that meet the criteria
+    #[serde(default)]
+    pub selection: Selection
+}
+
+/// How agents are chosen from those that meet the criteria of a Target
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum Selection {
+    /// Choose agents at random
+    #[default]
+    Random,
+    /// Choose the agents with the most links in their social network (degree centrality),
+    /// agents with the same number of links are chosen at random
+    MostConnected
+}
+
+impl Target {
+    /// Whether an agent meets the criteria of the target
+    /// * agent: The agent to check
+    /// * Returns: true iff the agent meets every criterion
+    pub fn matches(&self, agent: &Agent) -> bool {
+        (self.neighbourhoods.is_empty() || self.neighbourhoods.contains(&agent.neighbourhood_id))
+            && (self.commute_lengths.is_empty() || self.commute_lengths.contains(&agent.commute_length))
+            && (self.current_modes.is_empty() || self.current_modes.contains(&agent.current_mode))
+    }
 }
 
 /// This defines changes in the neighbourhood that may form part of an intervention
@@ -33,5 +96,142 @@ pub struct NeighbourhoodChange {
     /// This represents an increase in capacity of v for TransportMode k,
     /// where (k, v) are elements of the HashMap
     #[serde(default)]
-    pub increase_in_capacity: HashMap<TransportMode, i64>
-}
\ No newline at end of file
+    pub increase_in_capacity: HashMap<TransportMode, i64>,
+
+    /// Optional, this phases the changes in over a number of days, starting on the start day
+    /// of the intervention, if this is not given the changes are made on the start day
+    #[serde(default)]
+    pub ramp: Option<Ramp>
+}
+
+impl NeighbourhoodChange {
+    /// The part of the change that is made on a day, ramped changes are made over a number of days,
+    /// otherwise the whole change is made on the start day
+    /// * days_since_start: The number of days since the start day of the intervention, the start day is 0
+    /// * Returns: The increase in supportiveness and capacity, or None if no change is made on the day
+    pub fn increase_on_day(&self, days_since_start: u32) -> Option<(HashMap<TransportMode, f32>, HashMap<TransportMode, i64>)> {
+        let (progress, previous_progress) = match self.ramp {
+            Some(ref ramp) if days_since_start < ramp.days => {
+                let previous_progress = if days_since_start > 0 { ramp.progress(days_since_start - 1) } else { 0.0 };
+                (ramp.progress(days_since_start), previous_progress)
+            },
+            None if days_since_start == 0 => (1.0, 0.0),
+            _ => return None
+        };
+
+        // The part of the change is the change in progress since yesterday
+        let increase_in_supportiveness = self
+            .increase_in_supportiveness
+            .iter()
+            .map(|(&mode, &v)| (mode, (f64::from(v) * (progress - previous_progress)) as f32))
+            .collect();
+
+        // Capacity is whole, so round the total change so far and yesterday's total
+        let increase_in_capacity = self
+            .increase_in_capacity
+            .iter()
+            .map(|(&mode, &v)| {
+                let total = (v as f64 * progress).round() as i64;
+                let previous_total = (v as f64 * previous_progress).round() as i64;
+                (mode, total - previous_total)
+            })
+            .collect();
+
+        Some((increase_in_supportiveness, increase_in_capacity))
+    }
+}
+
+/// This defines how a change is phased in over a number of days
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Ramp {
+    /// The number of days over which the change is made
+    pub days: u32,
+
+    /// The shape of the curve the change follows
+    #[serde(default)]
+    pub shape: RampShape
+}
+
+/// The shape of the curve a phased in change follows
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+pub enum RampShape {
+    /// The same amount of the change is made each day
+    #[default]
+    Linear,
+    /// The change starts slowly, is fastest halfway through, then slows down (an S-curve)
+    Logistic
+}
+
+impl Ramp {
+    /// The proportion of the change that should have been made by a day
+    /// * days_since_start: The number of days since the start day of the intervention, the start day is 0
+    /// * Returns: The proportion from 0-1, this is 1 once the ramp has finished
+    pub fn progress(&self, days_since_start: u32) -> f64 {
+        // The start day is the first day of the ramp, so after it some of the change has been made
+        let t = (f64::from(days_since_start) + 1.0) / f64::from(self.days.max(1));
+        if t >= 1.0 {
+            return 1.0;
+        }
+
+        match self.shape {
+            RampShape::Linear => t,
+            RampShape::Logistic => logistic_progress(t)
+        }
+    }
+}
+
+/// A logistic curve centred on the middle of a ramp, rescaled to go from 0 to 1
+/// * t: The proportion of the ramp's days that have passed, from 0-1
+/// * Returns: The proportion of the change that should have been made, exactly 0 at the start and 1 at the end
+fn logistic_progress(t: f64) -> f64 {
+    let steepness = 10.0;
+    let logistic = |x: f64| 1.0 / (1.0 + (-steepness * (x - 0.5)).exp());
+    (logistic(t) - logistic(0.0)) / (logistic(1.0) - logistic(0.0))
+}
+
+/// This defines changes in a subculture that may form part of an intervention
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SubcultureChange {
+    /// Subculture ID
+    pub id: String,
+
+    /// Be very careful that this does not make the desirability > 1 or < 0  
+    /// This represents an increase in desirability of v for TransportMode k,
+    /// where (k, v) are elements of the HashMap
+    #[serde(default)]
+    pub increase_in_desirability: HashMap<TransportMode, f32>
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn logistic_progress_is_exactly_0_and_1_at_the_ends() {
+        assert_eq!(logistic_progress(0.0), 0.0);
+        assert_eq!(logistic_progress(1.0), 1.0);
+        assert!((logistic_progress(0.5) - 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn linear_ramp_makes_the_same_change_each_day() {
+        let ramp = Ramp { days: 4, shape: RampShape::Linear };
+        let progress: Vec<f64> = (0..6).map(|day| ramp.progress(day)).collect();
+        assert_eq!(progress, vec![0.25, 0.5, 0.75, 1.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn logistic_ramp_finishes_on_its_last_day() {
+        let ramp = Ramp { days: 10, shape: RampShape::Logistic };
+        assert!((ramp.progress(4) - 0.5).abs() < 1e-12);
+        assert_eq!(ramp.progress(9), 1.0);
+        assert_eq!(ramp.progress(100), 1.0);
+        assert!((0..9).all(|day| ramp.progress(day) < ramp.progress(day + 1)));
+    }
+
+    #[test]
+    fn ramp_of_0_days_is_made_on_the_start_day() {
+        let ramp = Ramp { days: 0, shape: RampShape::Logistic };
+        assert_eq!(ramp.progress(0), 1.0);
+    }
+}
@@ -1,7 +1,29 @@
 /// A categorical distance for commute
-#[derive(Eq, Hash, PartialEq, Copy, Clone, Serialize, Deserialize)]
+#[derive(Debug, Eq, Hash, PartialEq, Copy, Clone, Serialize, Deserialize)]
+#[allow(clippy::enum_variant_names)]
 pub enum JourneyType {
     LocalCommute,
     CityCommute,
     DistantCommute
-}
\ No newline at end of file
+}
+
+impl JourneyType {
+    /// Every journey type, from the shortest to the longest commute
+    pub fn all() -> [JourneyType; 3] {
+        [JourneyType::LocalCommute, JourneyType::CityCommute, JourneyType::DistantCommute]
+    }
+
+    /// The journey type of a commute distance
+    /// * distance: The commute distance in metres
+    /// * thresholds: The distances at which a LocalCommute becomes a CityCommute, and a CityCommute becomes a DistantCommute
+    /// * Returns: The journey type
+    pub fn from_distance(distance: f64, thresholds: (f64, f64)) -> JourneyType {
+        if distance < thresholds.0 {
+            JourneyType::LocalCommute
+        } else if distance < thresholds.1 {
+            JourneyType::CityCommute
+        } else {
+            JourneyType::DistantCommute
+        }
+    }
+}
@@ -2,16 +2,19 @@ extern crate itertools;
 #[macro_use] extern crate maplit;
 #[macro_use] extern crate log;
 #[macro_use] extern crate serde_derive;
-extern crate simple_logger;
+extern crate serde;
 extern crate serde_yaml;
 extern crate rand;
 extern crate rayon;
 extern crate hashmap_union;
 
 mod weather;
+mod rainfall;
+mod weather_fitting;
 mod transport_mode;
 mod journey_type;
 mod neighbourhood;
+mod subculture;
 mod scenario;
 mod agent;
 mod simulation;
@@ -21,6 +24,20 @@ mod statistics;
 mod gaussian;
 mod debug;
 mod agent_generation;
+mod initial_mode;
+mod rng;
+mod validation;
+mod check;
+mod error;
+mod paths;
+mod summary;
+mod comparison;
+mod ensemble;
+mod ranking;
+mod cost;
+mod optimisation;
+mod sweep;
+mod calibration;
 pub mod parameters;
 
 use std::fs::File;
@@ -29,132 +46,239 @@ use std::time::SystemTime;
 use std::io::Write;
 use std::io::prelude::*;
 use rayon::prelude::*;
-use weather::Weather;
+use rng::Stream;
 pub use parameters::Parameters;
+pub use weather::WeatherModel;
+use weather::WeatherSource;
+use weather::WeatherRealisations;
+pub use error::Error;
+use error::Problem;
+pub use paths::Paths;
+use scenario::Scenario;
+use optimisation::Optimisation;
+use sweep::Sweep;
+use calibration::Calibration;
 
 /// Read a social network from a file
 /// * file: An input file in YAML mapping ids to a list of ids
-/// * Returns: A HashMap mapping ids, to the ids of their friends
-pub fn read_network(mut file: File) -> HashMap<u32, Vec<u32>> {
+/// * Returns: A HashMap mapping ids, to the ids of their friends, or an Error if the file could not be read or parsed
+pub fn read_network(mut file: File) -> Result<HashMap<u32, Vec<u32>>, Error> {
     info!("READING NETWORK");
 
     // Create a new String (heap allocated) to store the contents of the file
     let mut file_contents = String::new();
 
     // Read the file into the String
-    file.read_to_string(&mut file_contents)
-        .expect("There was an error reading the file");
+    file.read_to_string(&mut file_contents)?;
 
     // Deserialize the network
-    serde_yaml::from_slice(file_contents.as_bytes())
-        .expect("There was an error parsing the file")
+    Ok(serde_yaml::from_slice(file_contents.as_bytes())?)
 }
 
-/// This generates a social network, and saves it them to YAML files in the networks/ subdirectory
+/// This generates a social network, and saves it them to YAML files in the networks directory
 /// * number_of_simulations_per_scenario: One network is generated per scenario
 /// * number_of_social_network_links: The minimum number of links each person in the social network has
 /// * number_of_people: The number of people in the simulation
+/// * seed: The master seed, network n is generated from the stream of simulation n
+/// * paths: Where the networks are saved
+/// * Returns: Nothing if successful, otherwise the Error writing the networks
 pub fn generate_and_save_networks(
     number_of_simulations_per_scenario: u32, 
     number_of_social_network_links: u32,
-    number_of_people: u32) 
+    number_of_people: u32,
+    seed: u64,
+    paths: &Paths) -> Result<(), Error>
 {
     // Generate as many social networks as number of simulations per scenario
-    let numbers: Vec<u32> = (0..number_of_simulations_per_scenario).collect();
+    let numbers: Vec<u32> = (1..=number_of_simulations_per_scenario).collect();
     // Get the networks stored as a YAML file
     let networks: Vec<String> = numbers
         .par_iter()
-        .map(|_| serde_yaml::to_string(&social_network::generate_social_network(
-            number_of_social_network_links, number_of_people)).unwrap())
+        .map(|&id| serde_yaml::to_string(&social_network::generate_social_network(
+            number_of_social_network_links, 
+            number_of_people,
+            &mut rng::create_rng(seed, id, Stream::SocialNetwork))).unwrap())
         .collect();
 
     // Create a networks directory to store them in
-    std::fs::create_dir_all("config/networks")
-        .expect("Failed to create config/networks directory");
+    std::fs::create_dir_all(&paths.networks)?;
 
     // For each network, save the network to a file
     networks
         .par_iter()
         .enumerate()
-        .for_each(|(i, item)| {
-            let mut file = std::fs::File::create(format!("config/networks/{}.yaml", i+1)).ok().unwrap();
-            file.write_all(item.as_bytes()).ok();
-        });
+        .map(|(i, item)| {
+            let mut file = File::create(paths.network_file(i as u32 + 1))?;
+            file.write_all(item.as_bytes())
+        })
+        .collect::<Result<Vec<()>, std::io::Error>>()?;
     
-    info!("Generating networks complete")
+    info!("Generating networks complete");
+
+    Ok(())
 }
 
+/// This generates the agents of each simulation, and saves them to YAML files in the agents directory  
+/// The agents are the same as those generated when running with generate
+/// * parameters: The parameters of the simulation
+/// * paths: Where the scenario is loaded from, and the agents are saved
+/// * Returns: Nothing if successful, otherwise the Error
+pub fn generate_and_save_agents(parameters: &Parameters, paths: &Paths) -> Result<(), Error> {
+    std::fs::create_dir_all(&paths.agents)?;
+
+    (1..=parameters.number_of_simulations)
+        .collect::<Vec<u32>>()
+        .par_iter()
+        .map(|&id| {
+            let scenario = Scenario::from_file(File::open(&paths.scenario)?)?;
+            agent_generation::generate_and_save_agents(
+                File::create(paths.agents_file(id))?,
+                &scenario,
+                parameters.social_connectivity,
+                parameters.subculture_connectivity,
+                parameters.neighbourhood_connectivity,
+                parameters.number_of_people,
+                parameters.distributions.clone(),
+                parameters.commute_length_thresholds,
+                &mut rng::create_rng(parameters.seed, id, Stream::Agents));
+            Ok(())
+        })
+        .collect::<Result<Vec<()>, Error>>()?;
+
+    info!("Generating agents complete");
+
+    Ok(())
+}
+
+/// Fits a Markov Chain weather model to observed rainfall, and saves it in the weather config format
+/// * rainfall_file: The path of a CSV file of date,rainfall (mm), transitions are not counted across gaps in the dates
+/// * template: The weather model whose states are used to classify the rainfall
+/// * order: The order of the Markov Chain, 1 or 2
+/// * output_file: The path of the YAML file to save the fitted weather model to, it is only created once fitting succeeds
+/// * Returns: Nothing if successful, otherwise the Error
+pub fn fit_weather_model(rainfall_file: &str, template: &WeatherModel, order: u32, output_file: &str) -> Result<(), Error> {
+    let series = rainfall::read_rainfall(rainfall_file)?;
+    let weather_model = weather_fitting::fit_weather_model(&series, template, order, rainfall_file)?;
+    weather_model.save(File::create(output_file)?)?;
+
+    info!("Fitting weather model complete");
+    Ok(())
+}
+
+/// Checks every configuration file
+/// * paths: Where the configuration files are
+/// * check_networks: Whether the social networks and agents should be checked, as they are when they are not generated
+/// * Returns: Nothing if there are no problems, otherwise Error::Invalid with every problem found
+pub fn check(paths: &Paths, check_networks: bool) -> Result<(), Error> {
+    check::check_config(
+        &paths.parameters,
+        &paths.scenario,
+        &paths.weather,
+        if check_networks { Some(&paths.networks) } else { None },
+        if check_networks { Some(&paths.agents) } else { None })
+}
+
+/// Summarises the output of every simulation, writing the ensemble summary of each day to ensemble_summary.csv,
+/// and the confidence interval of the final year active mode share to final_year_active_share.csv,
+/// in the output directory
+/// * parameters: The parameters of the simulation
+/// * paths: Where the output is
+/// * Returns: A table of the active mode share at the start and end of each simulation
+pub fn summarise(parameters: &Parameters, paths: &Paths) -> Result<String, Error> {
+    let table = summary::summarise(&paths.output, parameters.number_of_people)?;
+
+    File::create(format!("{}/ensemble_summary.csv", paths.output))?
+        .write_all(ensemble::summarise_days(&paths.output)?.as_bytes())?;
+    File::create(format!("{}/final_year_active_share.csv", paths.output))?
+        .write_all(ensemble::final_year_active_share_interval(&paths.output, parameters.number_of_people)?.as_bytes())?;
+    info!("Ensemble summary written to {}/ensemble_summary.csv", paths.output);
+
+    Ok(table)
+}
+
+/// Run the simulations in parallel
+/// * generate: Whether networks and agents should be generated
+/// * parameters: The parameters of the simulation
+/// * weather_model: The model used to generate the weather pattern
+/// * paths: Where the configuration is loaded from, and the output is saved
+/// * Returns: Nothing if successful, otherwise the Error, the configuration is checked before
+///   any simulation is run
 pub fn run_simulation(
     generate: bool,
-    parameters: Parameters,
-    )
+    parameters: &Parameters,
+    weather_model: &WeatherModel,
+    paths: &Paths
+    ) -> Result<(), Error>
 {
-    // Create a new logger for system output
-    simple_logger::init().unwrap();
-
     // Used for monitoring running time
     let t0 = SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
         .expect("Time went backwards")
         .as_secs();
 
+    // Check the configuration once, before any simulation runs, the networks
+    // are only checked if they are not about to be generated
+    check(paths, !generate)?;
+
     if generate {
         generate_and_save_networks(
                 parameters.number_of_simulations, 
                 parameters.number_of_social_network_links, 
-                parameters.number_of_people);
+                parameters.number_of_people,
+                parameters.seed,
+                paths)?;
             
         // Create a agents directory to store them in
-            std::fs::create_dir_all("config/agents")
-            .expect("Failed to create config/agents directory");
+        std::fs::create_dir_all(&paths.agents)?;
     }
 
-    let weather_transition_matrix = hashmap! {
-        Weather::Good => hashmap! {
-            Weather::Good => 0.886,
-            Weather::Bad => 0.114
-        },
-        Weather::Bad => hashmap! {
-            Weather::Good => 0.699,
-            Weather::Bad => 0.301
+    // Create the output directory if it does not already exist
+    std::fs::create_dir_all(&paths.output)?;
+
+    let days = (365 * parameters.total_years) as usize;
+
+    // A shared weather pattern is generated once, from the stream of simulation 0
+    let shared_weather_pattern = match weather_model.realisations {
+        WeatherRealisations::Shared => Some(weather_model.make_pattern(
+            days, &mut rng::create_rng(parameters.seed, 0, Stream::Weather))?),
+        WeatherRealisations::Independent => {
+            if let WeatherSource::Historical { .. } = weather_model.source {
+                warn!("Historical weather is the same in every simulation, even with independent realisations");
+            }
+            None
         }
     };
 
-    let weather_pattern = Weather::make_pattern(
-        weather_transition_matrix, 0.14, (365 * parameters.total_years) as usize);
-
     // Run in parallel the simulations
     (1..=parameters.number_of_simulations)
         .collect::<Vec<u32>>()
         .par_iter()
-        .for_each(|id| {
-            // Get the network number and load the network
-            let network_number = id.to_string();
-            let network_file = File::open(format!("config/networks/{}.yaml", network_number))
-                .expect("File cannot be opened");
-
-            let network = read_network(network_file);
-
-            let agent_file = if generate {
-                File::create(format!("config/agents/{}.yaml", network_number)).expect("File cannot be created")
-            } else {
-                File::open(format!("config/agents/{}.yaml", network_number)).expect("File cannot be opened")
+        .map(|&id| {
+            // Load the network
+            let network = read_network(File::open(paths.network_file(id))?)?;
+
+            // Use the shared weather pattern, or generate this simulation's own
+            let independent_weather_pattern;
+            let weather_pattern = match shared_weather_pattern {
+                Some(ref weather_pattern) => weather_pattern,
+                None => {
+                    independent_weather_pattern = weather_model.make_pattern(
+                        days, &mut rng::create_rng(parameters.seed, id, Stream::Weather))?;
+                    &independent_weather_pattern
+                }
             };
 
-            simulation::run(id.to_string(),
+            simulation::run(id,
                         generate,
-                        agent_file,
-                        File::open("config/scenario.yaml").ok().unwrap(),
-                        parameters.total_years,
-                        parameters.number_of_people,
-                        parameters.social_connectivity,
-                        parameters.neighbourhood_connectivity,
-                        parameters.number_of_neighbour_links,
-                        parameters.distributions.clone(),
-                        &weather_pattern,
+                        &paths.agents_file(id),
+                        File::open(&paths.scenario)?,
+                        File::create(paths.output_file(id))?,
+                        File::create(paths.mode_shares_file(id))?,
+                        parameters,
+                        weather_pattern,
                         network)
-                        .unwrap();
-    });
+        })
+        .collect::<Result<Vec<()>, Error>>()?;
 
     // Output the running time
 
@@ -162,5 +286,544 @@ pub fn run_simulation(
         .duration_since(std::time::UNIX_EPOCH)
         .expect("Time went backwards")
         .as_secs();
-    info!("TOTAL RUNNING TIME: {}s", t1 - t0)
-}
\ No newline at end of file
+    info!("TOTAL RUNNING TIME: {}s", t1 - t0);
+
+    Ok(())
+}
+
+/// Each scenario, and the paths used to run it
+type ScenarioPaths = Vec<(Scenario, Paths)>;
+
+/// Loads and checks several scenarios, and finds where their output goes
+/// * paths: Where the configuration is loaded from, and the output is saved
+/// * scenario_files: Scenario files, or directories of scenario files
+/// * check_networks: Whether the social networks and agents should be checked
+/// * Returns: Each scenario and its paths, and every problem found
+fn load_scenarios(paths: &Paths, scenario_files: &[String], check_networks: bool)
+    -> Result<(ScenarioPaths, Vec<Problem>), Error>
+{
+    let scenario_files = paths::find_scenario_files(scenario_files)?;
+
+    let mut problems = Vec::new();
+    let mut scenarios: ScenarioPaths = Vec::new();
+    for scenario_file in scenario_files.iter() {
+        let scenario = match File::open(scenario_file).map_err(Error::from).and_then(Scenario::from_file) {
+            Ok(scenario) => scenario,
+            Err(error) => {
+                problems.push(Problem::new(scenario_file.as_str(), "", error.to_string()));
+                continue;
+            }
+        };
+        let scenario_paths = paths.for_scenario(scenario_file, &scenario.id);
+
+        if scenarios.iter().any(|(_, other)| other.output == scenario_paths.output) {
+            problems.push(Problem::new(scenario_file.as_str(), "id", "another scenario has the same id"));
+        }
+
+        if let Err(Error::Invalid(scenario_problems)) = check(&scenario_paths, check_networks) {
+            problems.extend(scenario_problems);
+        }
+
+        scenarios.push((scenario, scenario_paths));
+    }
+
+    if scenario_files.is_empty() {
+        problems.push(Problem::new("", "", "no scenario files were found"));
+    }
+
+    Ok((scenarios, problems))
+}
+
+/// Runs scenarios that have been loaded and checked, each against the same networks and agents
+/// * generate: Whether networks and agents should be generated, the agents are generated using the scenario in paths
+/// * parameters: The parameters of the simulation
+/// * weather_model: The model used to generate the weather pattern
+/// * paths: Where the configuration is loaded from, and the output is saved
+/// * scenarios: Each scenario and its paths
+/// * Returns: Nothing if successful, otherwise the Error
+fn run_loaded_scenarios(
+    generate: bool,
+    parameters: &Parameters,
+    weather_model: &WeatherModel,
+    paths: &Paths,
+    scenarios: &[(Scenario, Paths)]) -> Result<(), Error>
+{
+    if generate {
+        generate_and_save_networks(
+            parameters.number_of_simulations,
+            parameters.number_of_social_network_links,
+            parameters.number_of_people,
+            parameters.seed,
+            paths)?;
+        generate_and_save_agents(parameters, paths)?;
+    }
+
+    for (_, scenario_paths) in scenarios.iter() {
+        info!("Running scenario {}", scenario_paths.scenario);
+        run_simulation(false, parameters, weather_model, scenario_paths)?;
+    }
+
+    Ok(())
+}
+
+/// Run several scenarios, each against the same networks and agents  
+/// The output of each scenario is written to a subdirectory of the output directory, named after the scenario
+/// * generate: Whether networks and agents should be generated, the agents are generated using the scenario in paths
+/// * parameters: The parameters of the simulation
+/// * weather_model: The model used to generate the weather pattern
+/// * paths: Where the configuration is loaded from, and the output is saved
+/// * scenario_files: Scenario files, or directories of scenario files
+/// * baseline: The ID of the scenario the others are compared to, replicate by replicate, if they should be
+/// * Returns: Nothing if successful, otherwise the Error, every scenario is checked before any is run
+pub fn run_scenarios(
+    generate: bool,
+    parameters: &Parameters,
+    weather_model: &WeatherModel,
+    paths: &Paths,
+    scenario_files: &[String],
+    baseline: Option<&str>) -> Result<(), Error>
+{
+    let (scenarios, mut problems) = load_scenarios(paths, scenario_files, !generate)?;
+
+    if let Some(baseline) = baseline {
+        if !scenarios.iter().any(|(scenario, _)| scenario.id == baseline) {
+            problems.push(Problem::new("--baseline", "", format!("there is no scenario with the ID {}", baseline)));
+        }
+    }
+
+    if !problems.is_empty() {
+        return Err(Error::Invalid(problems));
+    }
+
+    run_loaded_scenarios(generate, parameters, weather_model, paths, &scenarios)?;
+
+    // Every scenario was run against the same networks and agents, with the same random numbers,
+    // so replicate n of each scenario can be compared to replicate n of the baseline
+    if let Some(baseline) = baseline {
+        let outputs: Vec<(String, String)> = scenarios
+            .iter()
+            .map(|(scenario, scenario_paths)| (scenario.id.clone(), scenario_paths.output.clone()))
+            .collect();
+        let baseline_output = outputs.iter().find(|&(id, _)| id == baseline).unwrap();
+        let others: Vec<(String, String)> = outputs.iter().filter(|&(id, _)| id != baseline).cloned().collect();
+
+        let (differences, summary) = comparison::compare_to_baseline(baseline_output, &others, parameters.number_of_people)?;
+        File::create(format!("{}/paired_differences.csv", paths.output))?.write_all(differences.as_bytes())?;
+        File::create(format!("{}/paired_summary.csv", paths.output))?.write_all(summary.as_bytes())?;
+        info!("Differences from {} written to {}/paired_differences.csv", baseline, paths.output);
+    }
+
+    Ok(())
+}
+
+/// Ranks candidate intervention scenarios by the difference in active mode share from a baseline after their
+/// first intervention, writing the ranking to ranking.csv in the output directory  
+/// The baseline is the scenario in paths without its interventions, it is written to baseline.yaml in the output
+/// directory, and run with each candidate against the same networks and agents, so their replicates are paired
+/// * generate: Whether networks and agents should be generated, the agents are generated using the scenario in paths
+/// * parameters: The parameters of the simulation
+/// * weather_model: The model used to generate the weather pattern
+/// * paths: Where the configuration is loaded from, and the output is saved
+/// * scenario_files: The candidate scenario files, or directories of them
+/// * bootstrap_samples: The number of bootstrap samples used for the uncertainty of the ranks
+/// * Returns: The ranking table if successful, otherwise the Error, every candidate is checked before any is run
+pub fn rank_scenarios(
+    generate: bool,
+    parameters: &Parameters,
+    weather_model: &WeatherModel,
+    paths: &Paths,
+    scenario_files: &[String],
+    bootstrap_samples: u32) -> Result<String, Error>
+{
+    let (mut scenarios, mut problems) = load_scenarios(paths, scenario_files, !generate)?;
+
+    // The baseline has no interventions, so its replicates show how the model changes without them
+    let mut baseline = Scenario::from_file(File::open(&paths.scenario)?)?;
+    baseline.id = "baseline".to_string();
+    baseline.interventions.clear();
+    std::fs::create_dir_all(&paths.output)?;
+    let baseline_file = format!("{}/baseline.yaml", paths.output);
+    File::create(&baseline_file)?.write_all(serde_yaml::to_string(&baseline)?.as_bytes())?;
+    let baseline_paths = paths.for_scenario(&baseline_file, &baseline.id);
+    if let Err(Error::Invalid(baseline_problems)) = check(&baseline_paths, !generate) {
+        problems.extend(baseline_problems);
+    }
+
+    let mut candidates = Vec::new();
+    for (scenario, scenario_paths) in scenarios.iter() {
+        match scenario.interventions.iter().map(|intervention| intervention.start_day).min() {
+            Some(intervention_day) => candidates.push(ranking::Candidate {
+                id: scenario.id.clone(),
+                output_directory: scenario_paths.output.clone(),
+                intervention_day
+            }),
+            None => problems.push(Problem::new(scenario_paths.scenario.as_str(), "interventions", "a candidate must have an intervention"))
+        }
+        if scenario_paths.output == baseline_paths.output {
+            problems.push(Problem::new(scenario_paths.scenario.as_str(), "id", "baseline is used by the baseline of the ranking"));
+        }
+    }
+
+    if !problems.is_empty() {
+        return Err(Error::Invalid(problems));
+    }
+
+    let baseline_output = baseline_paths.output.clone();
+    scenarios.push((baseline, baseline_paths));
+    run_loaded_scenarios(generate, parameters, weather_model, paths, &scenarios)?;
+
+    let table = ranking::rank(&candidates, &baseline_output, parameters.number_of_people, bootstrap_samples, parameters.seed)?;
+    File::create(format!("{}/ranking.csv", paths.output))?.write_all(table.as_bytes())?;
+    info!("Ranking written to {}/ranking.csv", paths.output);
+
+    Ok(table)
+}
+
+/// Searches for the combination of intervention options, within a budget, that maximises the active mode share,
+/// using the scenario in paths with the options added to its interventions  
+/// Each combination is written to, and run from, the optimisation subdirectory of the output directory, with
+/// a table of every combination in evaluations.csv and the best combination in best_scenario.yaml
+/// * generate: Whether networks and agents should be generated
+/// * parameters: The parameters of the simulation
+/// * weather_model: The model used to generate the weather pattern
+/// * paths: Where the configuration is loaded from, and the output is saved
+/// * optimisation_file: The file of the options and budget
+/// * Returns: The table of every combination if successful, otherwise the Error
+pub fn optimise_interventions(
+    generate: bool,
+    parameters: &Parameters,
+    weather_model: &WeatherModel,
+    paths: &Paths,
+    optimisation_file: &str) -> Result<String, Error>
+{
+    let optimisation = Optimisation::from_file(File::open(optimisation_file)?)?;
+    let scenario = Scenario::from_file(File::open(&paths.scenario)?)?;
+    let mut problems = optimisation.check(optimisation_file);
+
+    let optimisation_paths = Paths {
+        output: format!("{}/optimisation", paths.output),
+        ..paths.clone()
+    };
+    std::fs::create_dir_all(&optimisation_paths.output)?;
+
+    // Writes the scenario with a combination of options added to its interventions
+    let write_combination = |id: &str, chosen: &[bool]| -> Result<Paths, Error> {
+        let mut combination = scenario.clone();
+        combination.id = id.to_string();
+        combination.interventions.extend(optimisation.interventions(chosen));
+        let scenario_file = format!("{}/{}.yaml", optimisation_paths.output, id);
+        File::create(&scenario_file)?.write_all(serde_yaml::to_string(&combination)?.as_bytes())?;
+        Ok(optimisation_paths.for_scenario(&scenario_file, id))
+    };
+
+    // Check the options with every one chosen, before any combination is run
+    let all_options = write_combination("all_options", &vec![true; optimisation.options.len()])?;
+    if let Err(Error::Invalid(scenario_problems)) = check(&all_options, !generate) {
+        problems.extend(scenario_problems);
+    }
+    std::fs::remove_file(&all_options.scenario)?;
+
+    if !problems.is_empty() {
+        return Err(Error::Invalid(problems));
+    }
+
+    if generate {
+        generate_and_save_networks(
+            parameters.number_of_simulations,
+            parameters.number_of_social_network_links,
+            parameters.number_of_people,
+            parameters.seed,
+            paths)?;
+        generate_and_save_agents(parameters, paths)?;
+    }
+
+    let mut combinations = 0;
+    let evaluations = optimisation::optimise(&optimisation, &scenario.costs, |chosen| {
+        combinations += 1;
+        let combination_paths = write_combination(&format!("combination_{}", combinations), chosen)?;
+        run_simulation(false, parameters, weather_model, &combination_paths)?;
+        let shares = comparison::final_year_active_shares(&combination_paths.output, parameters.number_of_people)?;
+        Ok(shares.values().sum::<f64>() / shares.len() as f64)
+    })?;
+
+    if let Some(best) = optimisation::best(&evaluations) {
+        info!("The best combination is {}, costing {}, with an active mode share of {}",
+            optimisation.describe(&best.chosen), best.cost, best.active_share);
+        let mut best_scenario = scenario.clone();
+        best_scenario.id = format!("{}_optimised", scenario.id);
+        // The options that were not chosen are kept, so every intervention draws the same random numbers as when it was scored
+        best_scenario.interventions.extend(optimisation.interventions(&best.chosen));
+        File::create(format!("{}/best_scenario.yaml", optimisation_paths.output))?
+            .write_all(serde_yaml::to_string(&best_scenario)?.as_bytes())?;
+    }
+
+    let table = optimisation::evaluations_table(&optimisation, &evaluations);
+    File::create(format!("{}/evaluations.csv", optimisation_paths.output))?.write_all(table.as_bytes())?;
+
+    Ok(table)
+}
+
+/// Writes the parameters of a point of a sweep or calibration, which has its own networks, agents and output
+/// * parameters: The parameters of the point
+/// * paths: Where the rest of the configuration is loaded from
+/// * directory: The directory of the point
+/// * Returns: The paths of the point if successful, otherwise the Error
+fn write_point(parameters: &Parameters, paths: &Paths, directory: &str) -> Result<Paths, Error> {
+    let point_paths = Paths {
+        parameters: format!("{}/parameters.yaml", directory),
+        networks: format!("{}/networks", directory),
+        agents: format!("{}/agents", directory),
+        output: directory.to_string(),
+        ..paths.clone()
+    };
+
+    std::fs::create_dir_all(directory)?;
+    File::create(&point_paths.parameters)?.write_all(serde_yaml::to_string(parameters)?.as_bytes())?;
+    Ok(point_paths)
+}
+
+/// Runs the simulations of a point of a sweep or calibration, generating its networks and agents
+/// * parameters: The parameters of the point
+/// * weather_model: The model used to generate the weather pattern
+/// * point_paths: The paths of the point
+/// * Returns: Nothing if successful, otherwise the Error
+fn run_point(parameters: &Parameters, weather_model: &WeatherModel, point_paths: &Paths) -> Result<(), Error> {
+    run_simulation(true, parameters, weather_model, point_paths)?;
+
+    // The networks and agents can be generated again from the parameters, so they are not kept
+    std::fs::remove_dir_all(&point_paths.networks)?;
+    std::fs::remove_dir_all(&point_paths.agents)?;
+    Ok(())
+}
+
+/// Runs the simulations at each point of a parameter sweep, and finds the sensitivity of the
+/// final year active mode share to each parameter swept  
+/// The parameters and output of point n are written to the sweep/point_n subdirectory of the output directory,
+/// with the parameters and active mode share of every point in sweep/points.csv, and for a Saltelli design
+/// the first-order and total-order Sobol indices of each parameter in sweep/sensitivity.csv
+/// * parameters: The parameters that are not swept
+/// * weather_model: The model used to generate the weather pattern
+/// * paths: Where the configuration is loaded from, and the output is saved
+/// * sweep_file: The file of the design and the range of each parameter swept
+/// * Returns: The Sobol indices for a Saltelli design, otherwise the table of every point, if successful,
+///   otherwise the Error, the parameters of every point are checked before any is run
+pub fn sweep_parameters(
+    parameters: &Parameters,
+    weather_model: &WeatherModel,
+    paths: &Paths,
+    sweep_file: &str) -> Result<String, Error>
+{
+    let sweep = Sweep::from_file(File::open(sweep_file)?)?;
+    let problems = sweep.check(sweep_file);
+    if !problems.is_empty() {
+        return Err(Error::Invalid(problems));
+    }
+
+    // Write the parameters of every point, and check them before any is run
+    let sweep_directory = format!("{}/sweep", paths.output);
+    let mut problems = Vec::new();
+    let mut points: Vec<(Parameters, Paths)> = Vec::new();
+    for (i, unit_point) in sweep.unit_points(parameters.seed).iter().enumerate() {
+        let point_parameters = sweep::parameters_at(&sweep.ranges, parameters, unit_point);
+        let point_paths = write_point(&point_parameters, paths, &format!("{}/point_{}", sweep_directory, i + 1))?;
+        if let Err(Error::Invalid(point_problems)) = check(&point_paths, false) {
+            problems.extend(point_problems);
+        }
+        points.push((point_parameters, point_paths));
+    }
+
+    if !problems.is_empty() {
+        return Err(Error::Invalid(problems));
+    }
+
+    let mut table = String::from("Point");
+    for range in sweep.ranges.iter() {
+        table.push_str(&format!(",{}", range.parameter.name()));
+    }
+    table.push_str(",ActiveShare\n");
+
+    let mut active_shares = Vec::new();
+    for (i, (point_parameters, point_paths)) in points.iter().enumerate() {
+        info!("Running point {} of {}", i + 1, points.len());
+        run_point(point_parameters, weather_model, point_paths)?;
+
+        let shares = comparison::final_year_active_shares(&point_paths.output, point_parameters.number_of_people)?;
+        let active_share = shares.values().sum::<f64>() / shares.len() as f64;
+        active_shares.push(active_share);
+
+        table.push_str(&format!("{}", i + 1));
+        for range in sweep.ranges.iter() {
+            table.push_str(&format!(",{}", range.parameter.value(point_parameters)));
+        }
+        table.push_str(&format!(",{}\n", active_share));
+    }
+    File::create(format!("{}/points.csv", sweep_directory))?.write_all(table.as_bytes())?;
+
+    if sweep.design == sweep::Design::Saltelli {
+        let mut sensitivity = String::from("Parameter,FirstOrder,TotalOrder\n");
+        for (range, (first_order, total_order)) in sweep.ranges.iter().zip(sweep::sobol_indices(sweep.ranges.len(), &active_shares)) {
+            sensitivity.push_str(&format!("{},{},{}\n", range.parameter.name(), first_order, total_order));
+        }
+        File::create(format!("{}/sensitivity.csv", sweep_directory))?.write_all(sensitivity.as_bytes())?;
+        return Ok(sensitivity);
+    }
+
+    Ok(table)
+}
+
+/// Fits parameters and initial mode probabilities to observed mode shares, by neighbourhood and commute length  
+/// The parameters, scenario and output of evaluation n are written to the calibration/evaluation_n subdirectory of the output directory,
+/// with the values and distance of every evaluation in calibration/evaluations.csv, the best parameters and scenario in
+/// calibration/fitted_parameters.yaml and calibration/fitted_scenario.yaml, and the observed and simulated shares of the best
+/// evaluation in calibration/fit_report.csv, approximate Bayesian computation also writes the accepted samples of each value
+/// in calibration/posterior.csv
+/// * parameters: The parameters that are not fitted
+/// * weather_model: The model used to generate the weather pattern
+/// * paths: Where the configuration is loaded from, and the output is saved
+/// * calibration_file: The file of the observed shares, method and the range of each value fitted
+/// * Returns: The fitted values and their distance from the observed shares if successful, otherwise the Error
+pub fn calibrate(
+    parameters: &Parameters,
+    weather_model: &WeatherModel,
+    paths: &Paths,
+    calibration_file: &str) -> Result<String, Error>
+{
+    let calibration = Calibration::from_file(File::open(calibration_file)?)?;
+    let mut problems = calibration.check(calibration_file);
+
+    let observed = match calibration::read_mode_shares(&calibration.observed) {
+        Ok(observed) => observed,
+        Err(Error::Invalid(observed_problems)) => {
+            problems.extend(observed_problems);
+            calibration::ModeShares::new()
+        },
+        Err(error) => {
+            problems.push(Problem::new(calibration.observed.as_str(), "", error.to_string()));
+            calibration::ModeShares::new()
+        }
+    };
+
+    let scenario = Scenario::from_file(File::open(&paths.scenario)?)?;
+    let has_neighbourhood = |id: &String| scenario.neighbourhoods.iter().any(|neighbourhood| &neighbourhood.id == id);
+    for (neighbourhood, _, _) in observed.keys().filter(|&(neighbourhood, _, _)| !has_neighbourhood(neighbourhood)) {
+        problems.push(Problem::new(calibration.observed.as_str(), "", format!("there is no neighbourhood {}", neighbourhood)));
+    }
+    problems.dedup_by(|a, b| a.to_string() == b.to_string());
+    for (i, range) in calibration.initial_modes.iter().enumerate() {
+        if let Some(neighbourhood) = range.neighbourhood.as_ref().filter(|id| !has_neighbourhood(id)) {
+            problems.push(Problem::new(calibration_file, format!("initial_modes[{}].neighbourhood", i),
+                format!("there is no neighbourhood {}", neighbourhood)));
+        }
+    }
+
+    if !problems.is_empty() {
+        return Err(Error::Invalid(problems));
+    }
+
+    // The parameters come first in a point, then the initial mode probabilities
+    let dimensions = calibration.ranges.len() + calibration.initial_modes.len();
+    let mut names: Vec<String> = calibration.ranges.iter().map(|range| range.parameter.name().to_string()).collect();
+    names.extend(calibration.initial_modes.iter().map(|range| range.name()));
+    let values = |point_parameters: &Parameters, point_scenario: &Scenario| -> Vec<f64> {
+        calibration.ranges
+            .iter()
+            .map(|range| range.parameter.value(point_parameters))
+            .chain(calibration.initial_modes.iter().map(|range| range.value(point_scenario)))
+            .collect()
+    };
+
+    let calibration_directory = format!("{}/calibration", paths.output);
+    let mut evaluations: Vec<(Parameters, Scenario, f64, calibration::ModeShares)> = Vec::new();
+    {
+        // Simulates a point, returning its distance from the observed shares
+        let mut evaluate = |unit_point: &[f64]| -> Result<f64, Error> {
+            let (parameter_point, initial_mode_point) = unit_point.split_at(calibration.ranges.len());
+            let point_parameters = sweep::parameters_at(&calibration.ranges, parameters, parameter_point);
+            let point_scenario = calibration::scenario_at(&calibration.initial_modes, &scenario, initial_mode_point);
+            let directory = format!("{}/evaluation_{}", calibration_directory, evaluations.len() + 1);
+            info!("Running evaluation {}", evaluations.len() + 1);
+
+            let mut point_paths = write_point(&point_parameters, paths, &directory)?;
+            if !calibration.initial_modes.is_empty() {
+                point_paths.scenario = format!("{}/scenario.yaml", directory);
+                File::create(&point_paths.scenario)?.write_all(serde_yaml::to_string(&point_scenario)?.as_bytes())?;
+            }
+            run_point(&point_parameters, weather_model, &point_paths)?;
+
+            let mode_share_files: Vec<String> = (1..=point_parameters.number_of_simulations)
+                .map(|id| point_paths.mode_shares_file(id))
+                .collect();
+            let simulated = calibration::mean_mode_shares(&mode_share_files)?;
+            let distance = calibration::distance(&observed, &simulated);
+            evaluations.push((point_parameters, point_scenario, distance, simulated));
+            Ok(distance)
+        };
+
+        match calibration.method {
+            calibration::Method::NelderMead =>
+                calibration::nelder_mead(dimensions, calibration.evaluations, &mut evaluate)?,
+            calibration::Method::ApproximateBayesianComputation => {
+                for unit_point in sweep::latin_hypercube(dimensions, calibration.samples as usize, parameters.seed).iter() {
+                    evaluate(unit_point)?;
+                }
+            }
+        }
+    }
+
+    let mut table = format!("Evaluation,{},Distance\n", names.join(","));
+    for (i, (point_parameters, point_scenario, distance, _)) in evaluations.iter().enumerate() {
+        let point_values: Vec<String> = values(point_parameters, point_scenario).iter().map(|value| value.to_string()).collect();
+        table.push_str(&format!("{},{},{}\n", i + 1, point_values.join(","), distance));
+    }
+    File::create(format!("{}/evaluations.csv", calibration_directory))?.write_all(table.as_bytes())?;
+
+    // The closest evaluations first, the first evaluation is used if several are equally close,
+    // an evaluation with a simulated share that is not a number has no distance, so it is not ranked
+    let mut ranked: Vec<&(Parameters, Scenario, f64, calibration::ModeShares)> = evaluations
+        .iter()
+        .filter(|evaluation| !evaluation.2.is_nan())
+        .collect();
+    ranked.sort_by(|a, b| a.2.total_cmp(&b.2));
+    let &&(ref fitted_parameters, ref fitted_scenario, distance, ref simulated) = match ranked.first() {
+        Some(fitted) => fitted,
+        None => return Err(Error::Invalid(vec![Problem::new(calibration_file, "",
+            "no evaluation could be compared with the observed mode shares, so nothing was fitted")]))
+    };
+
+    File::create(format!("{}/fitted_parameters.yaml", calibration_directory))?
+        .write_all(serde_yaml::to_string(fitted_parameters)?.as_bytes())?;
+    File::create(format!("{}/fitted_scenario.yaml", calibration_directory))?
+        .write_all(serde_yaml::to_string(fitted_scenario)?.as_bytes())?;
+    File::create(format!("{}/fit_report.csv", calibration_directory))?
+        .write_all(calibration::fit_report(&observed, simulated).as_bytes())?;
+
+    let mut fit = String::from("Parameter,Fitted\n");
+    for (name, value) in names.iter().zip(values(fitted_parameters, fitted_scenario)) {
+        fit.push_str(&format!("{},{}\n", name, value));
+    }
+
+    if calibration.method == calibration::Method::ApproximateBayesianComputation {
+        let accepted = &ranked[..ranked.len().min(calibration.accepted as usize)];
+        let accepted_values: Vec<Vec<f64>> = accepted
+            .iter()
+            .map(|&(point_parameters, point_scenario, _, _)| values(point_parameters, point_scenario))
+            .collect();
+
+        let mut posterior = String::from("Parameter,Mean,Sd,Minimum,Maximum\n");
+        for (i, name) in names.iter().enumerate() {
+            let samples: Vec<f64> = accepted_values.iter().map(|point_values| point_values[i]).collect();
+            let (mean, sd) = comparison::mean_and_sd(&samples);
+            posterior.push_str(&format!("{},{},{},{},{}\n",
+                name,
+                mean,
+                sd,
+                samples.iter().cloned().fold(f64::INFINITY, f64::min),
+                samples.iter().cloned().fold(f64::NEG_INFINITY, f64::max)));
+        }
+        File::create(format!("{}/posterior.csv", calibration_directory))?.write_all(posterior.as_bytes())?;
+        fit.push_str(&format!("\nAccepted {} of {} samples, with distances up to {}\n{}",
+            accepted.len(), ranked.len(), accepted[accepted.len() - 1].2, posterior));
+    }
+
+    Ok(format!("{}\nRoot mean square error of the fitted mode shares: {}\n", fit, distance))
+}
@@ -1,23 +1,279 @@
 extern crate motivatelib;
+extern crate clap;
+#[macro_use] extern crate log;
+extern crate rayon;
+extern crate simple_logger;
 
-use std::env;
 use std::fs::File;
+use std::process;
+use std::str::FromStr;
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use motivatelib::{Error, Parameters, Paths, WeatherModel};
 
-/// This is the entry point for the application
+/// This is the entry point for the application, run with `--help` for the subcommands and their flags
 fn main()
 {
-    let args: Vec<String> = env::args().collect();
-    let mut generate = false;
-    if args.len() >= 2 {
-        if &args[1] == "--generate" {
-            generate = true;
-        }
+    let matches = App::new("motivate")
+        .about("An agent-based model of active travel")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .setting(AppSettings::VersionlessSubcommands)
+        .arg(path_arg("parameters", "The parameters file", "config/parameters.yaml"))
+        .arg(path_arg("scenario", "The scenario file", "config/scenario.yaml"))
+        .arg(path_arg("weather", "The weather model file", "config/weather.yaml"))
+        .arg(path_arg("networks", "The directory of the social networks", "config/networks"))
+        .arg(path_arg("agents", "The directory of the agents", "config/agents"))
+        .arg(path_arg("output", "The directory of the output", "output"))
+        .arg(Arg::with_name("simulations")
+            .long("simulations")
+            .value_name("N")
+            .help("The number of simulations, instead of number_of_simulations in the parameters")
+            .global(true))
+        .arg(Arg::with_name("threads")
+            .long("threads")
+            .value_name("N")
+            .help("The number of threads simulations are run on, by default one per CPU")
+            .global(true))
+        .arg(Arg::with_name("log-level")
+            .long("log-level")
+            .value_name("LEVEL")
+            .possible_values(&["error", "warn", "info", "debug", "trace"])
+            .default_value("info")
+            .help("The most detailed messages that are logged")
+            .global(true))
+        .subcommand(SubCommand::with_name("generate-networks")
+            .about("Generate a social network for each simulation"))
+        .subcommand(SubCommand::with_name("generate-agents")
+            .about("Generate the agents of each simulation"))
+        .subcommand(SubCommand::with_name("run")
+            .about("Run the simulations")
+            .arg(Arg::with_name("generate")
+                .long("generate")
+                .help("Generate the networks and agents before running the simulations"))
+            .arg(Arg::with_name("scenarios")
+                .long("scenarios")
+                .value_name("PATH")
+                .multiple(true)
+                .help("Scenario files, or directories of them, to run against the same networks and agents, \
+                       the output of each is written to <output>/<scenario id>/"))
+            .arg(Arg::with_name("baseline")
+                .long("baseline")
+                .value_name("SCENARIO ID")
+                .requires("scenarios")
+                .help("Compare the final year active mode share of each scenario to this scenario, replicate by replicate, \
+                       written to <output>/paired_differences.csv and <output>/paired_summary.csv")))
+        .subcommand(SubCommand::with_name("rank")
+            .about("Run candidate intervention scenarios, and a baseline without interventions, against the same networks \
+                    and agents, and rank them by the difference in active mode share from the baseline after their \
+                    first intervention, written to <output>/ranking.csv")
+            .arg(Arg::with_name("candidates")
+                .required(true)
+                .multiple(true)
+                .help("The candidate scenario files, or directories of them, the output of each is written to <output>/<scenario id>/"))
+            .arg(Arg::with_name("generate")
+                .long("generate")
+                .help("Generate the networks and agents before running the candidates"))
+            .arg(Arg::with_name("bootstrap-samples")
+                .long("bootstrap-samples")
+                .value_name("N")
+                .default_value("1000")
+                .help("The number of bootstrap samples used for the uncertainty of the ranks")))
+        .subcommand(SubCommand::with_name("optimise")
+            .about("Search for the combination of intervention options, within a budget, that maximises the active mode share, \
+                    adding the options to the scenario, the output is written to <output>/optimisation/")
+            .arg(Arg::with_name("optimisation")
+                .required(true)
+                .help("A YAML file of the budget and the intervention options"))
+            .arg(Arg::with_name("generate")
+                .long("generate")
+                .help("Generate the networks and agents before running the combinations")))
+        .subcommand(SubCommand::with_name("sweep")
+            .about("Run the simulations at each point of a parameter sweep, generating the networks and agents of each point, \
+                    the output is written to <output>/sweep/")
+            .arg(Arg::with_name("sweep")
+                .required(true)
+                .help("A YAML file of the design and the range of each parameter swept")))
+        .subcommand(SubCommand::with_name("calibrate")
+            .about("Fit parameters to observed mode shares, by neighbourhood and commute length, generating the networks \
+                    and agents of each evaluation, the output is written to <output>/calibration/")
+            .arg(Arg::with_name("calibration")
+                .required(true)
+                .help("A YAML file of the observed mode shares, the method and the range of each parameter fitted")))
+        .subcommand(SubCommand::with_name("check")
+            .about("Check every configuration file, reporting every problem"))
+        .subcommand(SubCommand::with_name("summarise")
+            .about("Summarise the active mode share of each simulation in the output directory"))
+        .subcommand(SubCommand::with_name("fit-weather")
+            .about("Fit the weather model to observed rainfall, using the states of the weather model")
+            .arg(Arg::with_name("rainfall")
+                .required(true)
+                .help("A CSV file of date,rainfall (mm), transitions are not counted across gaps in the dates"))
+            .arg(Arg::with_name("fitted")
+                .required(true)
+                .help("The YAML file to save the fitted weather model to"))
+            .arg(Arg::with_name("order")
+                .long("order")
+                .possible_values(&["1", "2"])
+                .default_value("1")
+                .help("The order of the Markov Chain")))
+        .get_matches();
+
+    let (subcommand, subcommand_matches) = matches.subcommand();
+    // The global flags are set on the matches of the subcommand
+    let matches = subcommand_matches.unwrap_or(&matches);
+
+    // Create a new logger for system output
+    let level = log::Level::from_str(matches.value_of("log-level").unwrap()).unwrap();
+    simple_logger::init_with_level(level).unwrap();
+
+    if let Some(threads) = matches.value_of("threads") {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(parse(threads, "--threads"))
+            .build_global()
+            .expect("Failed to create the thread pool");
+    }
+
+    let paths = Paths {
+        parameters: matches.value_of("parameters").unwrap().to_string(),
+        scenario: matches.value_of("scenario").unwrap().to_string(),
+        weather: matches.value_of("weather").unwrap().to_string(),
+        networks: matches.value_of("networks").unwrap().to_string(),
+        agents: matches.value_of("agents").unwrap().to_string(),
+        output: matches.value_of("output").unwrap().to_string()
+    };
+
+    match subcommand {
+        "generate-networks" => {
+            let parameters = load_parameters(matches, &paths);
+            exit_on_error(motivatelib::generate_and_save_networks(
+                parameters.number_of_simulations,
+                parameters.number_of_social_network_links,
+                parameters.number_of_people,
+                parameters.seed,
+                &paths));
+        },
+        "generate-agents" => {
+            let parameters = load_parameters(matches, &paths);
+            exit_on_error(motivatelib::generate_and_save_agents(&parameters, &paths));
+        },
+        "run" => {
+            let parameters = load_parameters(matches, &paths);
+            let weather_model = load(&paths.weather, WeatherModel::from_file);
+            let generate = matches.is_present("generate");
+            match matches.values_of("scenarios") {
+                Some(scenario_files) => {
+                    let scenario_files: Vec<String> = scenario_files.map(|file| file.to_string()).collect();
+                    exit_on_error(motivatelib::run_scenarios(
+                        generate, &parameters, &weather_model, &paths, &scenario_files, matches.value_of("baseline")));
+                },
+                None => exit_on_error(motivatelib::run_simulation(generate, &parameters, &weather_model, &paths))
+            }
+        },
+        "rank" => {
+            let parameters = load_parameters(matches, &paths);
+            let weather_model = load(&paths.weather, WeatherModel::from_file);
+            let candidates: Vec<String> = matches.values_of("candidates").unwrap().map(|file| file.to_string()).collect();
+            print!("{}", exit_on_error(motivatelib::rank_scenarios(
+                matches.is_present("generate"),
+                &parameters,
+                &weather_model,
+                &paths,
+                &candidates,
+                parse(matches.value_of("bootstrap-samples").unwrap(), "--bootstrap-samples"))));
+        },
+        "optimise" => {
+            let parameters = load_parameters(matches, &paths);
+            let weather_model = load(&paths.weather, WeatherModel::from_file);
+            print!("{}", exit_on_error(motivatelib::optimise_interventions(
+                matches.is_present("generate"),
+                &parameters,
+                &weather_model,
+                &paths,
+                matches.value_of("optimisation").unwrap())));
+        },
+        "sweep" => {
+            let parameters = load_parameters(matches, &paths);
+            let weather_model = load(&paths.weather, WeatherModel::from_file);
+            print!("{}", exit_on_error(motivatelib::sweep_parameters(
+                &parameters, &weather_model, &paths, matches.value_of("sweep").unwrap())));
+        },
+        "calibrate" => {
+            let parameters = load_parameters(matches, &paths);
+            let weather_model = load(&paths.weather, WeatherModel::from_file);
+            print!("{}", exit_on_error(motivatelib::calibrate(
+                &parameters, &weather_model, &paths, matches.value_of("calibration").unwrap())));
+        },
+        "check" => {
+            exit_on_error(motivatelib::check(&paths, true));
+            println!("No problems were found");
+        },
+        "summarise" => {
+            let parameters = load_parameters(matches, &paths);
+            print!("{}", exit_on_error(motivatelib::summarise(&parameters, &paths)));
+        },
+        "fit-weather" => {
+            let weather_model = load(&paths.weather, WeatherModel::from_file);
+            exit_on_error(motivatelib::fit_weather_model(
+                matches.value_of("rainfall").unwrap(),
+                &weather_model,
+                parse(matches.value_of("order").unwrap(), "--order"),
+                matches.value_of("fitted").unwrap()));
+        },
+        _ => unreachable!("A subcommand is required")
     }
-    // Load parameters from file
-    let parameters = motivatelib::Parameters::from_file(
-        File::open("config/parameters.yaml")
-            .expect("Failed to open parameters file")
-    );
-
-    motivatelib::run_simulation(generate, parameters);
-}
\ No newline at end of file
+}
+
+/// Creates a flag for the path of a configuration file or directory
+/// * name: The name of the flag
+/// * help: What the path is
+/// * default: The default path
+/// * Returns: The flag
+fn path_arg<'a>(name: &'a str, help: &'a str, default: &'a str) -> Arg<'a, 'a> {
+    Arg::with_name(name)
+        .long(name)
+        .value_name("PATH")
+        .help(help)
+        .default_value(default)
+        .global(true)
+}
+
+/// Loads the parameters, overriding the number of simulations if --simulations is given
+/// * matches: The matches of the command line arguments
+/// * paths: Where the parameters are loaded from
+/// * Returns: The parameters, their seed is logged as it may have been chosen at random
+fn load_parameters(matches: &ArgMatches, paths: &Paths) -> Parameters {
+    let mut parameters = load(&paths.parameters, Parameters::from_file);
+    if let Some(simulations) = matches.value_of("simulations") {
+        parameters.number_of_simulations = parse(simulations, "--simulations");
+    }
+    info!("Using the seed {}", parameters.seed);
+    parameters
+}
+
+/// Loads a configuration file, exiting if it cannot be loaded
+/// * path: The path of the file
+/// * from_file: Loads the configuration from the file
+/// * Returns: The configuration
+fn load<T>(path: &str, from_file: fn(File) -> Result<T, Error>) -> T {
+    exit_on_error(File::open(path).map_err(Error::from).and_then(from_file))
+}
+
+/// Parses the value of a flag, exiting if it is not valid
+/// * value: The value of the flag
+/// * flag: The name of the flag
+/// * Returns: The parsed value
+fn parse<T: FromStr>(value: &str, flag: &str) -> T {
+    value.parse().unwrap_or_else(|_| {
+        eprintln!("Error: {} is not valid for {}", value, flag);
+        process::exit(1)
+    })
+}
+
+/// Exits with an error code, printing the error, if there is one
+/// * result: The result to check
+/// * Returns: The value of the result, if there is no error
+fn exit_on_error<T>(result: Result<T, Error>) -> T {
+    result.unwrap_or_else(|error| {
+        eprintln!("Error: {}", error);
+        process::exit(1)
+    })
+}
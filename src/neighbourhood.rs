@@ -5,6 +5,8 @@ use std::rc::Rc;
 use itertools::Itertools;
 use std::cell::RefCell;
 use transport_mode::TransportMode;
+use journey_type::JourneyType;
+use initial_mode::InitialModeProbabilities;
 use agent::Agent;
 
 // TODO: Is supportiveness the same as capacity
@@ -18,6 +20,11 @@ pub struct Neighbourhood {
     /// The maximum capacity for a transport mode, at which there is no congestion
     pub capacity: RefCell<HashMap<TransportMode, u32>>,
 
+    /// The probability of a resident starting with each mode, for each commute length,
+    /// instead of the probabilities of the scenario
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub initial_modes: HashMap<JourneyType, InitialModeProbabilities>,
+
     /// The calculated congestion modifier
     #[serde(skip, default = "default_congestion_modifier")]
     pub congestion_modifier: RefCell<HashMap<TransportMode, f32>>,
@@ -46,6 +53,7 @@ impl Default for Neighbourhood {
             id: "placeholder".to_string(),
             supportiveness: RefCell::new(HashMap::new()),
             capacity: RefCell::new(HashMap::new()),
+            initial_modes: HashMap::new(),
             congestion_modifier: RefCell::new(HashMap::new()),
             residents: RefCell::new(Vec::new())
         }
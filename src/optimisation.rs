@@ -0,0 +1,284 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::prelude::*;
+use serde_yaml;
+use cost::Costs;
+use error::Error;
+use error::Problem;
+use intervention::Intervention;
+use intervention::NeighbourhoodChange;
+use intervention::Target;
+
+/// The options an optimisation chooses between, and the budget they must fit within
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Optimisation {
+    /// The most the chosen options can cost, using the costs of the scenario
+    pub budget: f64,
+
+    /// The day the chosen options start
+    pub start_day: u32,
+
+    /// The options, each of which can be chosen at most once
+    pub options: Vec<InterventionOption>,
+
+    /// If there are at most this many combinations of options within the budget every combination is simulated,
+    /// otherwise options are added one at a time, choosing the option that increases the active mode share the most
+    #[serde(default = "default_maximum_combinations")]
+    pub maximum_combinations: u64
+}
+
+/// The default maximum number of combinations that are all simulated
+fn default_maximum_combinations() -> u64 {
+    64
+}
+
+/// An option that can be chosen by an optimisation, made as an intervention on the start day
+#[derive(Clone, Serialize, Deserialize)]
+pub struct InterventionOption {
+    /// The ID of the option
+    pub id: String,
+
+    /// Changes in the neighbourhood
+    #[serde(default)]
+    pub neighbourhood_changes: Vec<NeighbourhoodChange>,
+
+    /// Change in the number of bikes
+    #[serde(default)]
+    pub change_in_number_of_bikes: i32,
+
+    /// Change in the number of cars
+    #[serde(default)]
+    pub change_in_number_of_cars: i32,
+
+    /// The agents who may be given (or lose) bikes
+    #[serde(default)]
+    pub bike_target: Target,
+
+    /// The agents who may be given (or lose) cars
+    #[serde(default)]
+    pub car_target: Target
+}
+
+/// A combination of options that has been simulated
+pub struct Evaluation {
+    /// Whether each option was chosen, in the order of the options
+    pub chosen: Vec<bool>,
+    /// The cost of the chosen options
+    pub cost: f64,
+    /// The mean final year active mode share of the simulations, from 0-1
+    pub active_share: f64
+}
+
+impl Optimisation {
+    /// Loads an optimisation from a file
+    /// * file: A YAML file containing the Optimisation
+    /// * Returns: The optimisation, or an Error if the file could not be read or parsed
+    pub fn from_file(mut file: File) -> Result<Self, Error> {
+        let mut file_contents = String::new();
+        file.read_to_string(&mut file_contents)?;
+        Ok(serde_yaml::from_slice(file_contents.as_bytes())?)
+    }
+
+    /// Checks the optimisation, the interventions it makes are checked with the scenario
+    /// * file: The file the optimisation was loaded from
+    /// * Returns: Every problem found
+    pub fn check(&self, file: &str) -> Vec<Problem> {
+        let mut problems = Vec::new();
+
+        if self.budget < 0.0 {
+            problems.push(Problem::new(file, "budget", "should not be negative"));
+        }
+
+        if self.options.is_empty() {
+            problems.push(Problem::new(file, "options", "at least one option is needed"));
+        }
+
+        let mut ids = HashSet::new();
+        for (i, option) in self.options.iter().enumerate() {
+            if !ids.insert(&option.id) {
+                problems.push(Problem::new(file, format!("options[{}].id", i), format!("{} is used more than once", option.id)));
+            }
+        }
+
+        problems
+    }
+
+    /// The interventions made by a combination of options  
+    /// Every option has an intervention, those that are not chosen make no changes, so each option
+    /// is always in the same position and draws the same random numbers in every combination
+    /// * chosen: Whether each option is chosen
+    /// * Returns: The interventions
+    pub fn interventions(&self, chosen: &[bool]) -> Vec<Intervention> {
+        self.options
+            .iter()
+            .zip(chosen.iter())
+            .map(|(option, &chosen)| Intervention {
+                start_day: self.start_day,
+                end_day: None,
+                neighbourhood_changes: if chosen { option.neighbourhood_changes.clone() } else { Vec::new() },
+                subculture_changes: Vec::new(),
+                change_in_number_of_bikes: if chosen { option.change_in_number_of_bikes } else { 0 },
+                change_in_number_of_cars: if chosen { option.change_in_number_of_cars } else { 0 },
+                bike_target: option.bike_target.clone(),
+                car_target: option.car_target.clone()
+            })
+            .collect()
+    }
+
+    /// The cost of a combination of options
+    /// * chosen: Whether each option is chosen
+    /// * costs: The cost of each unit of change
+    /// * Returns: The total cost of the chosen options
+    pub fn cost(&self, chosen: &[bool], costs: &Costs) -> f64 {
+        self.interventions(chosen).iter().map(|intervention| costs.of_intervention(intervention)).sum()
+    }
+
+    /// The IDs of the chosen options
+    /// * chosen: Whether each option is chosen
+    /// * Returns: The IDs separated by semicolons, or "none"
+    pub fn describe(&self, chosen: &[bool]) -> String {
+        let ids: Vec<&str> = self.options
+            .iter()
+            .zip(chosen.iter())
+            .filter(|&(_, &chosen)| chosen)
+            .map(|(option, _)| option.id.as_str())
+            .collect();
+
+        if ids.is_empty() { "none".to_string() } else { ids.join(";") }
+    }
+}
+
+/// Searches for the combination of options within the budget that maximises the active mode share
+/// * optimisation: The options and budget
+/// * costs: The cost of each unit of change
+/// * evaluate: Simulates a combination of options, returning the mean final year active mode share
+/// * Returns: Every combination simulated, in the order they were simulated, starting with no options
+pub fn optimise<F>(optimisation: &Optimisation, costs: &Costs, mut evaluate: F) -> Result<Vec<Evaluation>, Error>
+    where F: FnMut(&[bool]) -> Result<f64, Error>
+{
+    let number_of_options = optimisation.options.len();
+    let mut evaluations = Vec::new();
+    let mut simulate = |chosen: Vec<bool>, evaluations: &mut Vec<Evaluation>| -> Result<f64, Error> {
+        let cost = optimisation.cost(&chosen, costs);
+        info!("Simulating {} (cost {})", optimisation.describe(&chosen), cost);
+        let active_share = evaluate(&chosen)?;
+        evaluations.push(Evaluation { chosen, cost, active_share });
+        Ok(active_share)
+    };
+
+    // Every combination within the budget, if there are not too many of them
+    let affordable = if number_of_options <= 20 {
+        let affordable: Vec<Vec<bool>> = (0..1u64 << number_of_options)
+            .map(|combination| (0..number_of_options).map(|i| combination & (1 << i) != 0).collect::<Vec<bool>>())
+            .filter(|chosen| optimisation.cost(chosen, costs) <= optimisation.budget)
+            .collect();
+        Some(affordable).filter(|affordable| affordable.len() as u64 <= optimisation.maximum_combinations)
+    } else {
+        None
+    };
+
+    match affordable {
+        Some(affordable) => {
+            for chosen in affordable {
+                simulate(chosen, &mut evaluations)?;
+            }
+        },
+        None => {
+            // Add the option that increases the active mode share the most, until none does
+            let mut chosen = vec![false; number_of_options];
+            let mut active_share = simulate(chosen.clone(), &mut evaluations)?;
+            loop {
+                let mut best: Option<(usize, f64)> = None;
+                for i in (0..number_of_options).filter(|&i| !chosen[i]) {
+                    let mut candidate = chosen.clone();
+                    candidate[i] = true;
+                    if optimisation.cost(&candidate, costs) > optimisation.budget {
+                        continue;
+                    }
+                    let candidate_share = simulate(candidate, &mut evaluations)?;
+                    if best.is_none_or(|(_, best_share)| candidate_share > best_share) {
+                        best = Some((i, candidate_share));
+                    }
+                }
+
+                match best {
+                    Some((i, best_share)) if best_share > active_share => {
+                        chosen[i] = true;
+                        active_share = best_share;
+                    },
+                    _ => break
+                }
+            }
+        }
+    }
+
+    Ok(evaluations)
+}
+
+/// The combination with the highest active mode share
+/// * evaluations: The combinations simulated
+/// * Returns: The best combination, the first is used if several are equal
+pub fn best(evaluations: &[Evaluation]) -> Option<&Evaluation> {
+    evaluations.iter().fold(None, |best: Option<&Evaluation>, evaluation| match best {
+        Some(best) if best.active_share >= evaluation.active_share => Some(best),
+        _ => Some(evaluation)
+    })
+}
+
+/// A table of the combinations simulated
+/// * optimisation: The options and budget
+/// * evaluations: The combinations simulated
+/// * Returns: A CSV of the options chosen, cost and active mode share of each combination
+pub fn evaluations_table(optimisation: &Optimisation, evaluations: &[Evaluation]) -> String {
+    let mut table = String::from("Combination,Options,Cost,ActiveShare\n");
+    for (i, evaluation) in evaluations.iter().enumerate() {
+        table.push_str(&format!("{},{},{},{}\n",
+            i + 1, optimisation.describe(&evaluation.chosen), evaluation.cost, evaluation.active_share));
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Three options that each cost 1, with a budget for two of them
+    fn optimisation(maximum_combinations: u64) -> Optimisation {
+        let mut optimisation: Optimisation = serde_yaml::from_str("
+            budget: 2
+            start_day: 0
+            options:
+              - {id: a, change_in_number_of_bikes: 1}
+              - {id: b, change_in_number_of_bikes: 1}
+              - {id: c, change_in_number_of_bikes: 1}").unwrap();
+        optimisation.maximum_combinations = maximum_combinations;
+        optimisation
+    }
+
+    /// Each option adds to the active mode share
+    fn active_share(chosen: &[bool]) -> Result<f64, Error> {
+        Ok(0.1 + [0.1, 0.3, 0.2].iter().zip(chosen.iter()).filter(|&(_, &chosen)| chosen).map(|(share, _)| share).sum::<f64>())
+    }
+
+    #[test]
+    fn every_combination_is_simulated_when_there_are_few_enough() {
+        let costs = Costs { bike: 1.0, ..Costs::default() };
+        let evaluations = optimise(&optimisation(7), &costs, active_share).unwrap();
+
+        // No options, each option, and each pair of options
+        assert_eq!(evaluations.len(), 7);
+        assert!(evaluations.iter().all(|evaluation| evaluation.cost <= 2.0));
+        assert_eq!(best(&evaluations).unwrap().chosen, vec![false, true, true]);
+    }
+
+    #[test]
+    fn options_are_added_one_at_a_time_when_there_are_too_many_combinations() {
+        let costs = Costs { bike: 1.0, ..Costs::default() };
+        let evaluations = optimise(&optimisation(6), &costs, active_share).unwrap();
+
+        // No options, then each of the three options, then b with each of the other two, c is added and a is over budget
+        let chosen: Vec<String> = evaluations.iter().map(|evaluation| optimisation(6).describe(&evaluation.chosen)).collect();
+        assert_eq!(chosen, vec!["none", "a", "b", "c", "a;b", "b;c"]);
+        assert_eq!(best(&evaluations).unwrap().chosen, vec![false, true, true]);
+    }
+}
@@ -1,8 +1,12 @@
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::prelude::*;
+use rand;
+use error::Error;
+use transport_mode::TransportMode;
 
 /// This stores the parameters of the model
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Parameters {
     /// Total number of years the simulation runs for
     pub total_years: u32,
@@ -12,6 +16,9 @@ pub struct Parameters {
     pub number_of_simulations: u32,
     /// How connected an agent is to their social network
     pub social_connectivity: f32,
+    /// How connected an agent is to their subculture, if this is left out subcultures have no influence
+    #[serde(default)]
+    pub subculture_connectivity: f32,
     /// How connected an agent is to their neighbourhood
     pub neighbourhood_connectivity: f32,
     /// The minimum number of links in their social network, and agent should have.
@@ -24,23 +31,47 @@ pub struct Parameters {
     /// for approximately 86% of the average
     pub days_in_habit_average: u32,
 
+    /// The master seed, every random number in the simulation is derived from it,
+    /// so the same parameters and seed always give the same output, if this is left out a random seed is used
+    #[serde(default = "random_seed")]
+    pub seed: u64,
+
     /// A vec of tuples (mean, sd, weight)
     /// Used for commute length
-    pub distributions: Vec<(f64, f64, f64)>
+    pub distributions: Vec<(f64, f64, f64)>,
+
+    /// The commute distances (in metres) at which a LocalCommute becomes a CityCommute,
+    /// and a CityCommute becomes a DistantCommute
+    #[serde(default = "default_commute_length_thresholds")]
+    pub commute_length_thresholds: (f64, f64),
+
+    /// The commute distance (in metres) at which the score of a mode halves, and halves again at twice the distance,
+    /// modes that are left out do not depend on the distance, if this is left out the commute length is used instead
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub distance_decay: Option<HashMap<TransportMode, f64>>
+}
+
+/// A seed from the operating system's source of randomness, for parameters without a seed,
+/// the seed is logged when the parameters are loaded, so the run can be repeated
+fn random_seed() -> u64 {
+    rand::random()
+}
+
+/// The default commute length thresholds
+fn default_commute_length_thresholds() -> (f64, f64) {
+    (4241.0, 19457.0)
 }
 
 impl Parameters {
     /// Loads Parameters from a file
     /// * file: The YAML file storing the serialized parameters
-    /// * Returns; The created parameters
-    pub fn from_file(mut file: File) -> Self {
+    /// * Returns: The created parameters, or an Error if the file could not be read or parsed
+    pub fn from_file(mut file: File) -> Result<Self, Error> {
         info!("Loading parameters from file");
         let mut file_contents = String::new();
 
-        file.read_to_string(&mut file_contents)
-            .expect("There was an error reading the file");
+        file.read_to_string(&mut file_contents)?;
 
-        serde_yaml::from_slice(file_contents.as_bytes())
-            .expect("There was an error parsing the file")
+        Ok(serde_yaml::from_slice(file_contents.as_bytes())?)
     }
 }
\ No newline at end of file
@@ -0,0 +1,95 @@
+use std::fs;
+use std::path::Path;
+use error::Error;
+
+/// Where the configuration is read from, and where the output is written to
+#[derive(Clone)]
+pub struct Paths {
+    /// The parameters file
+    pub parameters: String,
+    /// The scenario file
+    pub scenario: String,
+    /// The weather model file
+    pub weather: String,
+    /// The directory of the social networks, network n is n.yaml
+    pub networks: String,
+    /// The directory of the agents, the agents of simulation n are n.yaml
+    pub agents: String,
+    /// The directory of the output, the output of simulation n is output_n.csv, and its mode shares mode_shares_n.csv
+    pub output: String
+}
+
+impl Default for Paths {
+    fn default() -> Self {
+        Paths {
+            parameters: "config/parameters.yaml".to_string(),
+            scenario: "config/scenario.yaml".to_string(),
+            weather: "config/weather.yaml".to_string(),
+            networks: "config/networks".to_string(),
+            agents: "config/agents".to_string(),
+            output: "output".to_string()
+        }
+    }
+}
+
+impl Paths {
+    /// The social network file of a simulation
+    /// * id: The ID of the simulation
+    pub fn network_file(&self, id: u32) -> String {
+        format!("{}/{}.yaml", self.networks, id)
+    }
+
+    /// The agents file of a simulation
+    /// * id: The ID of the simulation
+    pub fn agents_file(&self, id: u32) -> String {
+        format!("{}/{}.yaml", self.agents, id)
+    }
+
+    /// The output file of a simulation
+    /// * id: The ID of the simulation
+    pub fn output_file(&self, id: u32) -> String {
+        format!("{}/output_{}.csv", self.output, id)
+    }
+
+    /// The mode share file of a simulation
+    /// * id: The ID of the simulation
+    pub fn mode_shares_file(&self, id: u32) -> String {
+        format!("{}/mode_shares_{}.csv", self.output, id)
+    }
+
+    /// The paths for one of several scenarios run against the same networks and agents,
+    /// its output is written to a subdirectory of the output directory, named after the scenario
+    /// * scenario_file: The scenario file
+    /// * scenario_id: The ID of the scenario
+    pub fn for_scenario(&self, scenario_file: &str, scenario_id: &str) -> Paths {
+        Paths {
+            scenario: scenario_file.to_string(),
+            output: format!("{}/{}", self.output, scenario_id),
+            ..self.clone()
+        }
+    }
+}
+
+/// Finds scenario files, the YAML files in a directory are used in order of name
+/// * paths: Scenario files, or directories of scenario files
+/// * Returns: The scenario files
+pub fn find_scenario_files(paths: &[String]) -> Result<Vec<String>, Error> {
+    let mut scenario_files = Vec::new();
+
+    for path in paths.iter() {
+        if Path::new(path).is_dir() {
+            let mut files: Vec<String> = fs::read_dir(path)?
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().is_some_and(|extension| extension == "yaml" || extension == "yml"))
+                .map(|path| path.to_string_lossy().into_owned())
+                .collect();
+            files.sort();
+            scenario_files.extend(files);
+        } else {
+            scenario_files.push(path.clone());
+        }
+    }
+
+    Ok(scenario_files)
+}
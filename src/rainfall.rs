@@ -0,0 +1,154 @@
+use std::fs::File;
+use std::io::prelude::*;
+use error::Error;
+use error::Problem;
+
+/// The rainfall observed on a day
+#[derive(Clone)]
+pub struct Rainfall {
+    /// The date of the observation, in the format YYYY-MM-DD
+    pub date: String,
+    /// The rainfall in mm, None if the observation is missing
+    pub rainfall: Option<f64>
+}
+
+/// Reads a daily rainfall series from a CSV file  
+/// The file should have a header, followed by rows of date,rainfall where the date is in the format
+/// YYYY-MM-DD and the rainfall is in mm. Missing observations can be left empty or be negative (e.g. -99.99)
+/// * rainfall_file: The path of the CSV file
+/// * Returns: The rainfall series, sorted by date, or an Error if the file could not be read, or has rows
+///   with an invalid date or rainfall
+pub fn read_rainfall(rainfall_file: &str) -> Result<Vec<Rainfall>, Error> {
+    info!("Loading rainfall from file");
+    let mut file_contents = String::new();
+
+    File::open(rainfall_file)?.read_to_string(&mut file_contents)?;
+
+    let mut problems = Vec::new();
+    let mut series: Vec<Rainfall> = Vec::new();
+    // The header is line 1
+    for (i, line) in file_contents.lines().enumerate().skip(1).filter(|(_, line)| !line.trim().is_empty()) {
+        let location = format!("line {}", i + 1);
+        let mut columns = line.split(',').map(|column| column.trim());
+
+        let date = columns.next().unwrap_or_default().to_string();
+        if day_number(&date).is_none() {
+            problems.push(Problem::new(rainfall_file, location.as_str(), format!("{} is not a date in the format YYYY-MM-DD", date)));
+        }
+
+        let rainfall = match columns.next().filter(|value| !value.is_empty()) {
+            Some(value) => match value.parse::<f64>() {
+                Ok(value) => Some(value).filter(|value| *value >= 0.0),
+                Err(_) => {
+                    problems.push(Problem::new(rainfall_file, location.as_str(), format!("the rainfall {} is not a number", value)));
+                    None
+                }
+            },
+            None => None
+        };
+
+        series.push(Rainfall { date, rainfall });
+    }
+
+    if !problems.is_empty() {
+        return Err(Error::Invalid(problems));
+    }
+
+    // Dates in the format YYYY-MM-DD sort in order when sorted as strings
+    series.sort_by(|a, b| a.date.cmp(&b.date));
+    Ok(series)
+}
+
+/// The number of days from 1970-01-01 to a date, so consecutive dates have consecutive numbers
+/// * date: The date, in the format YYYY-MM-DD
+/// * Returns: The number of days, or None if the date is not valid
+pub fn day_number(date: &str) -> Option<i64> {
+    let parts: Vec<&str> = date.split('-').collect();
+    if parts.len() != 3 {
+        return None;
+    }
+    let year: i64 = parts[0].parse().ok()?;
+    let month: i64 = parts[1].parse().ok()?;
+    let day: i64 = parts[2].parse().ok()?;
+
+    let leap_year = (year % 4 == 0 && year % 100 != 0) || year % 400 == 0;
+    let days_in_month = [31, if leap_year { 29 } else { 28 }, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31];
+    if !(1..=12).contains(&month) || day < 1 || day > days_in_month[month as usize - 1] {
+        return None;
+    }
+
+    // Count from 1st March, so the leap day is the last day of the year
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    Some(era * 146097 + day_of_era - 719468)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+
+    /// Writes a rainfall file to the temporary directory
+    /// * name: The name of the file, unique to the test
+    /// * contents: The contents of the file
+    /// * Returns: The path of the file
+    fn write_rainfall(name: &str, contents: &str) -> String {
+        let path = env::temp_dir().join(format!("motivate-{}-{}.csv", std::process::id(), name));
+        fs::write(&path, contents).unwrap();
+        path.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn day_number_counts_from_1970() {
+        assert_eq!(day_number("1970-01-01"), Some(0));
+        assert_eq!(day_number("1969-12-31"), Some(-1));
+        assert_eq!(day_number("2024-01-01"), Some(19723));
+    }
+
+    #[test]
+    fn day_number_handles_leap_years() {
+        assert_eq!(day_number("2020-03-01").unwrap() - day_number("2020-02-28").unwrap(), 2);
+        assert_eq!(day_number("2021-03-01").unwrap() - day_number("2021-02-28").unwrap(), 1);
+        assert!(day_number("2000-02-29").is_some());
+        assert_eq!(day_number("1900-02-29"), None);
+        assert_eq!(day_number("2021-02-29"), None);
+    }
+
+    #[test]
+    fn day_number_rejects_invalid_dates() {
+        for &date in ["2020-13-01", "2020-04-31", "2020-01-00", "2020-01", "01/01/2020", ""].iter() {
+            assert_eq!(day_number(date), None, "{} should not be a date", date);
+        }
+    }
+
+    #[test]
+    fn read_rainfall_treats_negative_and_empty_values_as_missing() {
+        let path = write_rainfall("missing", "date,rainfall\n2020-01-03,-99.99\n2020-01-01,1.5\n2020-01-02,\n\n2020-01-04,0\n");
+        let series = read_rainfall(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let dates: Vec<&str> = series.iter().map(|day| day.date.as_str()).collect();
+        assert_eq!(dates, vec!["2020-01-01", "2020-01-02", "2020-01-03", "2020-01-04"]);
+        let rainfall: Vec<Option<f64>> = series.iter().map(|day| day.rainfall).collect();
+        assert_eq!(rainfall, vec![Some(1.5), None, None, Some(0.0)]);
+    }
+
+    #[test]
+    fn read_rainfall_reports_every_invalid_row() {
+        let path = write_rainfall("invalid", "date,rainfall\n2020-01-01,1.0\n2020-02-30,2.0\n2020-01-03,wet\n");
+        let result = read_rainfall(&path);
+        fs::remove_file(&path).unwrap();
+
+        match result {
+            Err(Error::Invalid(problems)) => {
+                let locations: Vec<&str> = problems.iter().map(|problem| problem.location.as_str()).collect();
+                assert_eq!(locations, vec!["line 3", "line 4"]);
+            },
+            _ => panic!("the invalid rows should be reported")
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use std::collections::BTreeMap;
+use rand::Rng;
+use comparison::mean_and_sd;
+use ensemble::percentile;
+use error::Error;
+use error::Problem;
+use rng;
+use rng::Stream;
+use summary;
+
+/// A candidate intervention scenario, after it has been run
+pub struct Candidate {
+    /// The ID of the scenario
+    pub id: String,
+    /// The directory containing the output_n.csv files of the scenario
+    pub output_directory: String,
+    /// The day the first intervention of the scenario starts
+    pub intervention_day: u32
+}
+
+/// The active mode share of a simulation after an intervention
+/// * path: The path of the output CSV file
+/// * intervention_day: The day the intervention starts
+/// * number_of_people: The number of people in the simulation
+/// * Returns: The mean share of people using an active mode, from 0-1, from the intervention day onwards
+pub fn active_share_after_day(path: &str, intervention_day: u32, number_of_people: u32) -> Result<f64, Error> {
+    let (header, rows) = summary::read_output(path)?;
+    let day_column = summary::find_column(&header, "Day", path)?;
+    let active_mode_column = summary::find_column(&header, "ActiveMode", path)?;
+
+    let parse = |row: &Vec<String>, column: usize| -> Result<f64, Error> {
+        row.get(column)
+            .and_then(|value| value.parse::<f64>().ok())
+            .ok_or_else(|| Error::Invalid(vec![Problem::new(path, "", "a row is not a number")]))
+    };
+
+    let mut after = Vec::new();
+    for row in rows.iter() {
+        if parse(row, day_column)? >= f64::from(intervention_day) {
+            after.push(parse(row, active_mode_column)?);
+        }
+    }
+
+    if after.is_empty() {
+        return Err(Error::Invalid(vec![Problem::new(path, "",
+            format!("the output must have days on or after day {}", intervention_day))]));
+    }
+
+    Ok(after.iter().sum::<f64>() / after.len() as f64 / f64::from(number_of_people.max(1)))
+}
+
+/// Ranks candidate intervention scenarios by how much they raise the active mode share above a baseline
+/// without interventions, after their intervention day  
+/// Replicate n of every candidate and the baseline used the same network, agents and random numbers, so each
+/// replicate is compared with the same replicate of the baseline, over the days from the candidate's intervention
+/// day onwards, this removes the drift of the model as it settles, so candidates with different intervention days
+/// can be compared. The bootstrap resamples replicates, keeping the candidates paired, to find how certain each rank is
+/// * candidates: The candidates, which must have the same replicates
+/// * baseline_directory: The directory containing the output_n.csv files of the baseline
+/// * number_of_people: The number of people in each simulation
+/// * bootstrap_samples: The number of bootstrap samples
+/// * seed: The master seed, the bootstrap draws from its own stream
+/// * Returns: A CSV of the candidates in order of rank, with the mean and sd of the difference from the baseline,
+///   and the mean rank, 5th and 95th percentile rank and the probability of being ranked first across the bootstrap samples
+pub fn rank(
+    candidates: &[Candidate],
+    baseline_directory: &str,
+    number_of_people: u32,
+    bootstrap_samples: u32,
+    seed: u64) -> Result<String, Error>
+{
+    let baseline_outputs: BTreeMap<u32, String> = summary::find_outputs(baseline_directory)?.into_iter().collect();
+
+    // The difference from the baseline of each replicate of each candidate
+    let mut problems = Vec::new();
+    let mut changes: Vec<BTreeMap<u32, f64>> = Vec::new();
+    for candidate in candidates.iter() {
+        let mut candidate_changes = BTreeMap::new();
+        for (replicate, path) in summary::find_outputs(&candidate.output_directory)? {
+            match baseline_outputs.get(&replicate) {
+                Some(baseline_path) => {
+                    let share = active_share_after_day(&path, candidate.intervention_day, number_of_people)?;
+                    let baseline_share = active_share_after_day(baseline_path, candidate.intervention_day, number_of_people)?;
+                    candidate_changes.insert(replicate, share - baseline_share);
+                },
+                None => problems.push(Problem::new(candidate.output_directory.as_str(), "",
+                    format!("replicate {} has no baseline replicate", replicate)))
+            }
+        }
+        changes.push(candidate_changes);
+    }
+
+    let replicates: Vec<u32> = match changes.first() {
+        Some(first) => first.keys().cloned().collect(),
+        None => return Err(Error::Invalid(vec![Problem::new("", "", "there are no candidates to rank")]))
+    };
+
+    if replicates.is_empty() {
+        problems.push(Problem::new(candidates[0].output_directory.as_str(), "", "there are no outputs to rank"));
+    }
+    for (candidate, candidate_changes) in candidates.iter().zip(changes.iter()) {
+        if !candidate_changes.keys().eq(replicates.iter()) {
+            problems.push(Problem::new(candidate.output_directory.as_str(), "",
+                format!("the replicates are not the same as {}", candidates[0].output_directory)));
+        }
+    }
+    if !problems.is_empty() {
+        return Err(Error::Invalid(problems));
+    }
+
+    // Resample the same replicates for every candidate, and rank the mean changes of the sample
+    let mut rng = rng::create_rng(seed, 0, Stream::Bootstrap);
+    let mut bootstrap_ranks: Vec<Vec<f64>> = vec![Vec::with_capacity(bootstrap_samples as usize); candidates.len()];
+    for _ in 0..bootstrap_samples {
+        let sample: Vec<u32> = (0..replicates.len())
+            .map(|_| replicates[rng.gen_range(0, replicates.len())])
+            .collect();
+        let means: Vec<f64> = changes
+            .iter()
+            .map(|candidate_changes| sample.iter().map(|replicate| candidate_changes[replicate]).sum::<f64>() / sample.len() as f64)
+            .collect();
+        for (candidate, ranks) in bootstrap_ranks.iter_mut().enumerate() {
+            ranks.push(1.0 + means.iter().filter(|&&mean| mean > means[candidate]).count() as f64);
+        }
+    }
+
+    let mut rows: Vec<(usize, f64, f64)> = changes
+        .iter()
+        .enumerate()
+        .map(|(candidate, candidate_changes)| {
+            let (mean, sd) = mean_and_sd(&candidate_changes.values().cloned().collect::<Vec<f64>>());
+            (candidate, mean, sd)
+        })
+        .collect();
+    // The highest mean difference first, a mean that is not a number is ranked last
+    rows.sort_by(|a, b| a.1.is_nan().cmp(&b.1.is_nan()).then(b.1.total_cmp(&a.1)));
+
+    let mut table = String::from(
+        "Rank,Scenario,InterventionDay,Replicates,MeanDifference,SdDifference,MeanRank,RankP5,RankP95,ProbabilityBest\n");
+    for (rank, &(candidate, mean, sd)) in rows.iter().enumerate() {
+        let ranks = &mut bootstrap_ranks[candidate];
+        ranks.sort_by(|a, b| a.total_cmp(b));
+        let (mean_rank, p5, p95, best) = if ranks.is_empty() {
+            (f64::NAN, f64::NAN, f64::NAN, f64::NAN)
+        } else {
+            (ranks.iter().sum::<f64>() / ranks.len() as f64,
+             percentile(ranks, 5.0),
+             percentile(ranks, 95.0),
+             ranks.iter().filter(|&&rank| rank == 1.0).count() as f64 / ranks.len() as f64)
+        };
+        table.push_str(&format!("{},{},{},{},{},{},{},{},{},{}\n",
+            rank + 1,
+            candidates[candidate].id,
+            candidates[candidate].intervention_day,
+            replicates.len(),
+            mean,
+            sd,
+            mean_rank,
+            p5,
+            p95,
+            best));
+    }
+
+    Ok(table)
+}
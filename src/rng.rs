@@ -0,0 +1,83 @@
+use rand::prng::ChaChaRng;
+use rand::SeedableRng;
+
+/// The random number generator used throughout the simulation  
+/// ChaCha is used as its output is the same on every platform
+pub type SimulationRng = ChaChaRng;
+
+/// The independent streams of random numbers in a simulation  
+/// Each part of the simulation draws from its own stream, so that a change in how many numbers
+/// one part draws does not change the numbers drawn by any other part
+#[derive(Clone, Copy)]
+pub enum Stream {
+    /// Generating the social network
+    SocialNetwork,
+    /// Generating the agents
+    Agents,
+    /// Linking agents to their neighbours
+    Neighbours,
+    /// Generating the weather pattern
+    Weather,
+    /// Running the interventions, each intervention has its own generator, see create_indexed_rng
+    Intervention,
+    /// Resampling replicates when ranking scenarios
+    Bootstrap,
+    /// Sampling the points of a parameter sweep
+    Sweep
+}
+
+/// Create a random number generator for a stream of a simulation  
+/// The seed of the generator is derived from the master seed, the simulation id and the stream,
+/// so the numbers drawn do not depend on the order in which simulations are run
+/// * seed: The master seed
+/// * simulation_id: The ID of the simulation, 0 is used for anything shared between simulations
+/// * stream: The stream the numbers are drawn for
+/// * Returns: The seeded random number generator
+pub fn create_rng(seed: u64, simulation_id: u32, stream: Stream) -> SimulationRng {
+    // Mix the simulation id and stream into the master seed
+    let mut state = seed;
+    state = split_mix(&mut state) ^ u64::from(simulation_id);
+    state = split_mix(&mut state) ^ stream as u64;
+
+    SimulationRng::from_seed(expand_seed(state))
+}
+
+/// Create a random number generator for one of several items drawing from a stream of a simulation  
+/// Each item has its own generator, so the numbers drawn for an item do not depend on the other items,
+/// e.g. the same intervention draws the same numbers in every scenario it is in
+/// * seed: The master seed
+/// * simulation_id: The ID of the simulation
+/// * stream: The stream the numbers are drawn for
+/// * index: The index of the item
+/// * Returns: The seeded random number generator
+pub fn create_indexed_rng(seed: u64, simulation_id: u32, stream: Stream, index: u32) -> SimulationRng {
+    let mut state = seed;
+    state = split_mix(&mut state) ^ u64::from(simulation_id);
+    state = split_mix(&mut state) ^ stream as u64;
+    state = split_mix(&mut state) ^ u64::from(index);
+
+    SimulationRng::from_seed(expand_seed(state))
+}
+
+/// Expand a state into a full ChaCha seed
+/// * state: The mixed state
+/// * Returns: The seed
+fn expand_seed(mut state: u64) -> [u8; 32] {
+    let mut rng_seed = [0u8; 32];
+    for chunk in rng_seed.chunks_mut(8) {
+        chunk.copy_from_slice(&split_mix(&mut state).to_le_bytes());
+    }
+    rng_seed
+}
+
+/// Advance a SplitMix64 generator, used to turn a simple seed into a well mixed seed
+/// <http://xorshift.di.unimi.it/splitmix64.c>
+/// * state: The state of the generator
+/// * Returns: The next number from the generator
+fn split_mix(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
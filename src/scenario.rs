@@ -1,15 +1,31 @@
+use std::collections::HashMap;
 use std::rc::Rc;
+use std::cell::RefCell;
 use std::fs::File;
 use std::io::prelude::*;
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::de;
 use serde_yaml;
 use neighbourhood::Neighbourhood;
+use subculture::Subculture;
+use transport_mode::TransportMode;
+use journey_type::JourneyType;
+use initial_mode::InitialModeProbabilities;
 use intervention::Intervention;
+use validation::Bounds;
+use cost::Costs;
+use error::Error;
 
 /// A scenario for a simulation run
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Scenario {
     /// The scenario ID
     pub id: String,
+    /// The subcultures in the scenario
+    #[serde(default = "default_subcultures")]
+    pub subcultures: Vec<Rc<Subculture>>,
+
     /// The neighbourhoods in the scenario
     pub neighbourhoods: Vec<Rc<Neighbourhood>>,
 
@@ -19,24 +35,57 @@ pub struct Scenario {
     /// The number of cars in the scenario
     pub number_of_cars: u32,
 
-    /// The intervention
-    pub intervention: Intervention
+    /// The probability of an agent starting with each mode, for each commute length, neighbourhoods can override these
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub initial_modes: HashMap<JourneyType, InitialModeProbabilities>,
+
+    /// The interventions, which take place in order of start day, scenarios written before there could be several
+    /// interventions give one, as intervention
+    #[serde(alias = "intervention", deserialize_with = "one_or_many")]
+    pub interventions: Vec<Intervention>,
+
+    /// What to do when the interventions take supportiveness or capacity out of bounds
+    #[serde(default)]
+    pub bounds: Bounds,
+
+    /// The cost of each unit of change an intervention can make
+    #[serde(default)]
+    pub costs: Costs
+}
+
+/// The subcultures of scenarios written before there were subcultures, every agent belongs to one subculture
+/// that finds no mode desirable, so with the default subculture_connectivity of 0 it has no influence
+fn default_subcultures() -> Vec<Rc<Subculture>> {
+    vec![Rc::new(Subculture {
+        id: "None".to_string(),
+        desirability: RefCell::new(TransportMode::all().iter().map(|&mode| (mode, 0.0)).collect())
+    })]
+}
+
+/// Deserializes a list of interventions, or a single intervention as a list of one
+fn one_or_many<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Intervention>, D::Error> {
+    let value = serde_yaml::Value::deserialize(deserializer)?;
+    let interventions = match value {
+        serde_yaml::Value::Sequence(_) => serde_yaml::from_value(value),
+        _ => serde_yaml::from_value(value).map(|intervention| vec![intervention])
+    };
+    // The line and column of an error are counted from the start of the interventions
+    interventions.map_err(|error| de::Error::custom(format!("interventions: {}", error)))
 }
 
 impl Scenario {
     /// Loads a scenario from a file
     /// * file: A YAML file containing the Scenario
-    pub fn from_file(mut file: File) -> Self {
+    /// * Returns: The scenario, or an Error if the file could not be read or parsed
+    pub fn from_file(mut file: File) -> Result<Self, Error> {
         info!("Loading scenario from file");
         // Create an empty String (heap allocated) to store the file's contents
         let mut file_contents = String::new();
 
         // Read the file into the string
-        file.read_to_string(&mut file_contents)
-            .expect("There was an error reading the file");
+        file.read_to_string(&mut file_contents)?;
 
         // Deserialize the string to a Scenario
-        serde_yaml::from_slice(file_contents.as_bytes())
-            .expect("There was an error parsing the file")
+        Ok(serde_yaml::from_slice(file_contents.as_bytes())?)
     }
 }
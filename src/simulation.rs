@@ -1,14 +1,11 @@
-extern crate rand;
-
 use std;
 use std::collections::HashMap;
+use std::collections::BTreeMap;
 use std::io::Write;
 use std::io::BufWriter;
 use itertools::Itertools;
 use std::time::SystemTime;
-use std::fs;
-use std::io;
-use rand::thread_rng;
+use rand::Rng;
 use rand::seq::sample_slice_ref;
 use std::rc::Rc;
 use std::cell::RefCell;
@@ -17,36 +14,45 @@ use weather::Weather;
 use transport_mode::TransportMode;
 use journey_type::JourneyType;
 use scenario::Scenario;
+use neighbourhood::Neighbourhood;
+use subculture::Subculture;
+use intervention::Intervention;
+use intervention::Target;
+use intervention::Selection;
 use agent::Agent;
 use statistics;
 use hashmap_union::union_of;
 use social_network;
 use agent_generation;
+use parameters::Parameters;
+use rng;
+use rng::Stream;
+use validation;
+use error::Error;
+use validation::Bounds;
+use validation::BoundsPolicy;
 
 /// Run the simulation
 /// * id: The ID of the simulation
 /// * generate: Whether agents should be generated
-/// * agents_file: The file for the agents
+/// * agents_file: The path of the file for the agents
 /// * scenario_file: The scenario of the simulation 
-/// * number_of_people: The number of agents to generate
-/// * social_connectivity: How connected the agent is to its social network
-/// * neighbourhood_connectivity: How connected the agent is to its neighbourhood
-/// * number_of_neighbour_links: The minimum number of links each agent should have in the neighbourhood network
-/// * weather_pattern: A HashMap from day number to Weather
+/// * output_file: The file the output CSV is written to
+/// * mode_shares_file: The file the share of each mode, by neighbourhood and commute length, over the final year is written to
+/// * parameters: The parameters of the simulation
+/// * weather_pattern: A Vec where the index is the day
 /// * network: The social network
-/// * Returns: Result, nothing if successful, io:Error if output could not be written
-pub fn run(id: String,
+/// * Returns: Result, nothing if successful, Error if the scenario could not be loaded or output could not be written
+#[allow(clippy::too_many_arguments)]
+pub fn run(id: u32,
            generate: bool,
-           agents_file: File,
+           agents_file: &str,
            scenario_file: File,
-           total_years: u32,
-           number_of_people: u32,
-           social_connectivity: f32,
-           neighbourhood_connectivity: f32,
-           number_of_neighbour_links: u32,
-           distributions: Vec<(f64, f64, f64)>,
-           weather_pattern: &Vec<Weather>,
-           network: HashMap<u32, Vec<u32>>) -> Result<(), io::Error> 
+           output_file: File,
+           mode_shares_file: File,
+           parameters: &Parameters,
+           weather_pattern: &[Weather],
+           network: HashMap<u32, Vec<u32>>) -> Result<(), Error> 
 {
     // Used for monitoring running time
     let t0 = SystemTime::now()
@@ -55,21 +61,29 @@ pub fn run(id: String,
         .as_secs();
 
     // Load scenario
-    let scenario = Scenario::from_file(scenario_file);
+    let scenario = Scenario::from_file(scenario_file)?;
+
+    // Each part of the simulation draws from its own stream of random numbers
+    let mut agents_rng = rng::create_rng(parameters.seed, id, Stream::Agents);
+    let mut neighbours_rng = rng::create_rng(parameters.seed, id, Stream::Neighbours);
 
     let mut residents: Vec<Rc<RefCell<Agent>>> = if generate {
         agent_generation::generate_and_save_agents(
-            agents_file, 
+            File::create(agents_file)?, 
             &scenario, 
-            social_connectivity, 
-            neighbourhood_connectivity, 
-            number_of_people, 
-            distributions)
+            parameters.social_connectivity, 
+            parameters.subculture_connectivity, 
+            parameters.neighbourhood_connectivity, 
+            parameters.number_of_people, 
+            parameters.distributions.clone(),
+            parameters.commute_length_thresholds,
+            &mut agents_rng)
     } else {
-        agent_generation::load_unlinked_agents_from_file(agents_file, &scenario.neighbourhoods)
+        agent_generation::load_unlinked_agents_from_file(
+            File::open(agents_file)?, agents_file, &scenario.neighbourhoods, &scenario.subcultures)?
     };
 
-    link_agents(&residents, number_of_neighbour_links, network);
+    link_agents(&residents, parameters.number_of_neighbour_links, network, &mut neighbours_rng);
 
     // Report the setup running time
     let t1 = SystemTime::now()
@@ -78,24 +92,54 @@ pub fn run(id: String,
         .as_secs();
     info!("[{}] Agents created in {}s", id, t1-t0);
 
-    // Create the output directory if it does not already exist
-    fs::create_dir_all("output")?;
-
-    // Create the output file, and write the header to it
-    let mut file = BufWriter::new(fs::File::create(format!("output/output_{}.csv", id))?);
-    file.write(generate_csv_header(&scenario).as_bytes())?;
+    // Write the header to the output file
+    let mut file = BufWriter::new(output_file);
+    file.write_all(generate_csv_header(&scenario).as_bytes())?;
 
     // Get the weather at day 0
-    let mut weather = weather_pattern[0];
+    let mut weather = &weather_pattern[0];
 
     // Write the first set of statistics to the file
-    file.write(generate_csv_output(0, &weather, &scenario, &residents).as_bytes())?;
+    file.write_all(generate_csv_output(0, weather, &scenario, &residents).as_bytes())?;
+
+    // The changes made by each intervention, while it is running
+    let mut applied_interventions: Vec<Option<AppliedIntervention>> = scenario
+        .interventions
+        .iter()
+        .map(|_| None)
+        .collect();
+
+    // The number of agents taking each mode, summed over the weekdays of the final year
+    let mut final_year_mode_counts: HashMap<(String, JourneyType, TransportMode), usize> = HashMap::new();
+    let mut final_year_days = 0;
 
     // For each day in the simulation
-    for day in 1..total_years * 365 {
-        // Intervene at the intervention day
-        if day == scenario.intervention.day {
-            intervene(&scenario, &residents)
+    for day in 1..parameters.total_years * 365 {
+        // Revert the interventions that end today, then run those that start today
+        for (intervention, applied) in scenario.interventions.iter().zip(applied_interventions.iter_mut()) {
+            if intervention.end_day == Some(day) {
+                if let Some(applied) = applied.take() {
+                    info!("[{}] Reverting intervention", id);
+                    revert_intervention(applied, scenario.bounds);
+                }
+            }
+        }
+
+        for (i, (intervention, applied)) in scenario.interventions.iter().zip(applied_interventions.iter_mut()).enumerate() {
+            if intervention.start_day == day {
+                info!("[{}] Intervening", id);
+                // Each intervention has its own random numbers, so they are the same in every scenario
+                // with the intervention in the same position
+                let mut intervention_rng = rng::create_indexed_rng(parameters.seed, id, Stream::Intervention, i as u32);
+                *applied = Some(intervene(intervention, &scenario, &residents, &mut intervention_rng));
+            }
+        }
+
+        // Make the changes to neighbourhoods that are due today
+        for (intervention, applied) in scenario.interventions.iter().zip(applied_interventions.iter_mut()) {
+            if let Some(ref mut applied) = *applied {
+                change_neighbourhoods(intervention, applied, day, scenario.bounds);
+            }
         }
 
         // Only consider weekdays
@@ -104,7 +148,14 @@ pub fn run(id: String,
             info!("[{}] Day: {}", id, day);
 
             // Get the new weather
-            let new_weather = weather_pattern[day as usize];
+            let new_weather = &weather_pattern[day as usize];
+
+            // Remember how each resident travelled on the previous day
+            for resident in residents.iter() {
+                let mut resident = resident.borrow_mut();
+                resident.update_last_mode();
+                resident.update_habit(parameters.days_in_habit_average);
+            }
 
             // Update neighbourhood congestion modifier
             for neighbourhood in scenario.neighbourhoods.iter() {
@@ -113,17 +164,26 @@ pub fn run(id: String,
 
             // For each resident, choose a travel mode
             for resident in residents.iter_mut() {
-                resident.borrow_mut().choose(&new_weather, weather != new_weather);
+                resident.borrow_mut().choose(new_weather, weather != new_weather, parameters.distance_decay.as_ref());
             }
 
             // Update the weather
             weather = new_weather;
 
             // Log the stats to the file
-            file.write(generate_csv_output(day, &weather, &scenario, &residents).as_bytes())?;
+            file.write_all(generate_csv_output(day, weather, &scenario, &residents).as_bytes())?;
+
+            if day >= (parameters.total_years - 1) * 365 {
+                for (key, count) in statistics::count_modes_by_neighbourhood_and_commute_length(&residents) {
+                    *final_year_mode_counts.entry(key).or_insert(0) += count;
+                }
+                final_year_days += 1;
+            }
         }
     }
 
+    write_mode_shares(mode_shares_file, &scenario, &residents, &final_year_mode_counts, final_year_days)?;
+
     // Output the running time to the terminal
     let t2 = SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
@@ -141,24 +201,29 @@ pub fn run(id: String,
 /// * agents: The agents to link
 /// * number_of_neighbour_links: The minimum number of links each agent should have in the neighbourhood network
 /// * network: The social network
-fn link_agents(agents: &[Rc<RefCell<Agent>>],
+/// * rng: The random number generator
+fn link_agents<R: Rng>(agents: &[Rc<RefCell<Agent>>],
           number_of_neighbour_links: u32,
-          network: HashMap<u32, Vec<u32>>) {
+          network: HashMap<u32, Vec<u32>>,
+          rng: &mut R) {
     
     // Load pre-generated social networks
     link_agents_from_predefined_network(agents, network, |agent, friends| agent.social_network.append(friends));
     agents.iter()
         .for_each(|a| debug!("Social network size for agent: {}", a.borrow().social_network.len()));
 
-    // Group agents by neighbourhood
-    let neighbourhood_residents: HashMap<String, Vec<Rc<RefCell<Agent>>>> = agents
+    // Group agents by neighbourhood, in order of neighbourhood id so that the
+    // neighbourhood networks are always generated in the same order
+    let neighbourhood_residents: BTreeMap<String, Vec<Rc<RefCell<Agent>>>> = agents
         .iter()
         .map(|x| (x.borrow().neighbourhood.id.clone(), x.clone()))
-        .into_group_map();
+        .into_group_map()
+        .into_iter()
+        .collect();
 
     // For each neighbourhood create a neighbourhood social network
     for (_, v) in neighbourhood_residents {
-        link_agents_to_neighbours(&v, number_of_neighbour_links);
+        link_agents_to_neighbours(&v, number_of_neighbour_links, rng);
     }
 }
 
@@ -167,9 +232,10 @@ fn link_agents(agents: &[Rc<RefCell<Agent>>],
 /// <https://en.wikipedia.org/wiki/Barab%C3%A1si%E2%80%93Albert_model>
 /// * agents: a slice of agents
 /// * n: the minimum number of links
-fn link_agents_to_neighbours(agents: &[Rc<RefCell<Agent>>], n: u32) {
+/// * rng: The random number generator
+fn link_agents_to_neighbours<R: Rng>(agents: &[Rc<RefCell<Agent>>], n: u32, rng: &mut R) {
     // Create network of ids
-    let network = social_network::generate_social_network(n, agents.len() as u32);
+    let network = social_network::generate_social_network(n, agents.len() as u32, rng);
     // Create the neighbourhood network from the network of ids
     link_agents_from_predefined_network(agents, network, |agent, friends| agent.neighbours.append(friends));
 
@@ -181,6 +247,12 @@ fn link_agents_to_neighbours(agents: &[Rc<RefCell<Agent>>], n: u32) {
 /// * scenario: The scenario for this simulation
 /// * Returns: The header for the csv file
 fn generate_csv_header(scenario: &Scenario) -> String {
+    let subculture_ids: Vec<String> = scenario
+        .subcultures
+        .iter()
+        .map(|subculture| subculture.id.clone())
+        .collect();
+
     let neighbourhood_ids: Vec<String> = scenario
         .neighbourhoods
         .iter()
@@ -188,11 +260,52 @@ fn generate_csv_header(scenario: &Scenario) -> String {
         .collect();
 
     format!(
-        "Day,Rain,ActiveMode,LocalCommute,CityCommute,DistantCommute,{}\n",
+        "Day,Rain,ActiveMode,ActiveNorm,ActiveModeCounterToInactiveNorm,InactiveModeCounterToActiveNorm,LocalCommute,CityCommute,DistantCommute,{},{}\n",
+        subculture_ids.join(","),
         neighbourhood_ids.join(",")
     )
 }
 
+/// Write the share of agents taking each mode, by neighbourhood and commute length, averaged over some days
+/// * mode_shares_file: The file to write the CSV to
+/// * scenario: The scenario
+/// * agents: The agents
+/// * mode_counts: The number of agents taking each mode, by neighbourhood and commute length, summed over the days
+/// * days: The number of days
+/// * Returns: Nothing if successful, otherwise the Error writing the file
+fn write_mode_shares(
+    mode_shares_file: File,
+    scenario: &Scenario,
+    agents: &[Rc<RefCell<Agent>>],
+    mode_counts: &HashMap<(String, JourneyType, TransportMode), usize>,
+    days: usize) -> Result<(), Error>
+{
+    let mut group_sizes: HashMap<(String, JourneyType), usize> = HashMap::new();
+    for agent in agents.iter() {
+        let agent = agent.borrow();
+        *group_sizes.entry((agent.neighbourhood_id.clone(), agent.commute_length)).or_insert(0) += 1;
+    }
+
+    let mut file = BufWriter::new(mode_shares_file);
+    file.write_all(b"Neighbourhood,CommuteLength,Mode,Share\n")?;
+    for neighbourhood in scenario.neighbourhoods.iter() {
+        for &journey_type in JourneyType::all().iter() {
+            // Groups without agents have no share
+            let group_size = match group_sizes.get(&(neighbourhood.id.clone(), journey_type)) {
+                Some(&group_size) => group_size,
+                None => continue
+            };
+            for &mode in TransportMode::all().iter() {
+                let count = mode_counts.get(&(neighbourhood.id.clone(), journey_type, mode)).cloned().unwrap_or(0);
+                file.write_all(format!("{},{:?},{:?},{}\n",
+                    neighbourhood.id, journey_type, mode, count as f64 / (group_size * days.max(1)) as f64).as_bytes())?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
 /// Generate CSV output that conforms to the header generated in generate_csv_header(...)
 /// * day: The day number
 /// * weather: The current weather
@@ -200,30 +313,44 @@ fn generate_csv_header(scenario: &Scenario) -> String {
 /// * agents: The agents in the network
 /// * Returns: The csv output for the day
 fn generate_csv_output(day: u32, weather: &Weather, scenario: &Scenario, agents: &[Rc<RefCell<Agent>>]) -> String {
-    let rain = if weather == &Weather::Good { 0 } else { 1 };
+    // The Rain column reports the position of the weather state in the weather model
+    let rain = weather.index;
 
     let active_mode = statistics::count_active_mode(agents);
+    let norm_counts = statistics::count_norms(agents);
     let active_mode_by_commute_length = statistics::count_active_mode_by_commute_length(agents);
-    let local_commute = active_mode_by_commute_length.get(&JourneyType::LocalCommute).unwrap();
-    let city_commute = active_mode_by_commute_length.get(&JourneyType::CityCommute).unwrap();
-    let distant_commute = active_mode_by_commute_length.get(&JourneyType::DistantCommute).unwrap();
+    let local_commute = active_mode_by_commute_length.get(&JourneyType::LocalCommute).cloned().unwrap_or(0);
+    let city_commute = active_mode_by_commute_length.get(&JourneyType::CityCommute).cloned().unwrap_or(0);
+    let distant_commute = active_mode_by_commute_length.get(&JourneyType::DistantCommute).cloned().unwrap_or(0);
+    let active_mode_by_subculture = statistics::count_active_mode_by_subculture(agents);
+
+    let active_mode_by_subculture_in_correct_order: Vec<String> = scenario
+        .subcultures
+        .iter()
+        .map(|subculture| active_mode_by_subculture.get(&subculture.id).unwrap_or(&0usize).to_string())
+        .collect();
+
     let active_mode_by_neighbourhood =
         statistics::count_active_mode_by_neighbourhood(&scenario.neighbourhoods);
 
     let active_mode_by_neighbourhood_in_correct_order: Vec<String> = scenario
         .neighbourhoods
         .iter()
-        .map(|neighbourhood| active_mode_by_neighbourhood.get(neighbourhood).unwrap_or(&0usize).to_string())
+        .map(|neighbourhood| active_mode_by_neighbourhood.get(&neighbourhood.id).unwrap_or(&0usize).to_string())
         .collect();
 
     format!(
-        "{},{},{},{},{},{},{},\n",
+        "{},{},{},{},{},{},{},{},{},{},{},\n",
         day,
         rain,
         active_mode,
+        norm_counts.active_norm,
+        norm_counts.active_mode_counter_to_inactive_norm,
+        norm_counts.inactive_mode_counter_to_active_norm,
         local_commute,
         city_commute,
         distant_commute,
+        active_mode_by_subculture_in_correct_order.join(","),
         active_mode_by_neighbourhood_in_correct_order.join(",")
     )
 }
@@ -259,139 +386,239 @@ fn weekday(day: u32) -> bool {
     day % 7 < 5
 }
 
-/// This will run the intervention definined in scenario
-/// * scenario: The scenario containing the interventions
+/// The changes an intervention made, so that they can be reverted at the end of the intervention
+struct AppliedIntervention {
+    /// The increase in supportiveness made to each neighbourhood
+    supportiveness: Vec<(Rc<Neighbourhood>, HashMap<TransportMode, f32>)>,
+    /// The increase in capacity made to each neighbourhood, after the capacity was kept within the bounds of a u32
+    capacity: Vec<(Rc<Neighbourhood>, HashMap<TransportMode, i64>)>,
+    /// The increase in desirability made to each subculture
+    desirability: Vec<(Rc<Subculture>, HashMap<TransportMode, f32>)>,
+    /// The agents whose bike ownership changed, and whether they owned a bike before
+    bike_owners: Vec<(Rc<RefCell<Agent>>, bool)>,
+    /// The agents whose car ownership changed, and whether they owned a car before
+    car_owners: Vec<(Rc<RefCell<Agent>>, bool)>
+}
+
+/// This will run an intervention defined in the scenario
+/// * intervention: The intervention to run
+/// * scenario: The scenario containing the intervention
 /// * agents: The agents in the simulation
-fn intervene(scenario: &Scenario, agents: &[Rc<RefCell<Agent>>]) {
-    // This adds Intervention.neighbourhood_changes.increase_in_supportiveness 
-    // to Neighbourhood.supportiveness
-    scenario
-        .intervention
-        .neighbourhood_changes
-        .iter()
-        .for_each(
-            |change| {
-                let neighbourhood_to_change = scenario
-                    .neighbourhoods
-                    .iter()
-                    .filter(|neighbourhood| neighbourhood.id == change.id)
-                    .next()
-                    .expect("A neighbourhood in your intervention was not found");
-
-                let new_supportiveness = union_of(
-                    &neighbourhood_to_change.supportiveness.borrow(),
-                    &change.increase_in_supportiveness, 
-                    |v1, v2| v1 + v2);
-
-                neighbourhood_to_change.supportiveness.replace(new_supportiveness);
-
-                // Convert capacity for an u32 to i64 for use later (i64 has a higher max value than u32, which has a higher max than i32)
-                let signed_capacity: HashMap<TransportMode, i64> = neighbourhood_to_change
-                    .capacity
-                    .borrow()
-                    .iter()
-                    .map(|(&k, &v)| (k, v as i64))
-                    .collect();
-        
-                // Calculate the new capacity, and convert to u32
-                let new_capacity: HashMap<TransportMode, u32> = union_of(
-                        &signed_capacity, &change.increase_in_capacity, |v1, v2| v1 + v2
-                    )
-                    .into_iter()
-                    .map(|(k, v)| {
-                        if v < 0 {
-                            (k, 0)
-                        } else if v > u32::max_value() as i64 {
-                            (k, u32::max_value())
-                        } else {
-                            (k, v as u32)
-                        }
-                    })
-                    .collect();
-
-                neighbourhood_to_change.capacity.replace(new_capacity);
-            }
-        );
-    
-    if scenario.intervention.change_in_number_of_bikes > 0 {
-        // Give people bikes
-        
-        // Filter agents without bikes
-        let agents_without_bikes: Vec<&Rc<RefCell<Agent>>> = agents
-            .iter()
-            .filter(|agent| !agent.borrow().owns_bike)
-            .collect();
+/// * rng: The random number generator
+/// * Returns: The changes made, so that they can be reverted
+fn intervene<R: Rng>(
+    intervention: &Intervention, 
+    scenario: &Scenario, 
+    agents: &[Rc<RefCell<Agent>>], 
+    rng: &mut R) -> AppliedIntervention
+{
+    let mut applied = AppliedIntervention {
+        supportiveness: Vec::new(),
+        capacity: Vec::new(),
+        desirability: Vec::new(),
+        bike_owners: Vec::new(),
+        car_owners: Vec::new()
+    };
 
-        // Choose a random sample, the size of the increase, and give them bikes
-        let mut rng = thread_rng();
-        let sample = sample_slice_ref(
-            &mut rng, 
-            &agents_without_bikes, 
-            scenario.intervention.change_in_number_of_bikes as usize);
-        
-        sample
+    // The changes to neighbourhoods are made in change_neighbourhoods, from the start day,
+    // as they may be phased in over a number of days
+    for change in intervention.neighbourhood_changes.iter() {
+        let neighbourhood_to_change = scenario
+            .neighbourhoods
             .iter()
-            .for_each(|agent| agent.borrow_mut().owns_bike = true);
-    } else if scenario.intervention.change_in_number_of_bikes < 0 {
-        // Take away some bikes
+            .find(|neighbourhood| neighbourhood.id == change.id)
+            .expect("A neighbourhood in your intervention was not found");
 
-        // Filter agents with bikes
-        let agents_with_bikes: Vec<&Rc<RefCell<Agent>>> = agents
-            .iter()
-            .filter(|agent| agent.borrow().owns_bike)
-            .collect();
+        applied.supportiveness.push((Rc::clone(neighbourhood_to_change), HashMap::new()));
+        applied.capacity.push((Rc::clone(neighbourhood_to_change), HashMap::new()));
+    }
 
-        // Choose a random sample, the size of the decrease, and take away bikes
-        let mut rng = thread_rng();
-        let decrease_in_bikes = (scenario.intervention.change_in_number_of_bikes * -1) as usize;
-        let sample = sample_slice_ref(
-            &mut rng, 
-            &agents_with_bikes, 
-            decrease_in_bikes);
-        
-        sample
+    // This adds Intervention.subculture_changes.increase_in_desirability
+    // to Subculture.desirability
+    for change in intervention.subculture_changes.iter() {
+        let subculture_to_change = scenario
+            .subcultures
             .iter()
-            .for_each(|agent| agent.borrow_mut().owns_bike = false);
+            .find(|subculture| subculture.id == change.id)
+            .expect("A subculture in your intervention was not found");
+
+        change_desirability(subculture_to_change, &change.increase_in_desirability);
+        applied.desirability.push((Rc::clone(subculture_to_change), change.increase_in_desirability.clone()));
     }
 
-    if scenario.intervention.change_in_number_of_cars > 0 {
-        // Give people cars
+    // Give people (or take away) bikes and cars
+    applied.bike_owners = change_ownership(
+        agents, intervention.change_in_number_of_bikes, &intervention.bike_target, |agent| &mut agent.owns_bike, rng);
+    applied.car_owners = change_ownership(
+        agents, intervention.change_in_number_of_cars, &intervention.car_target, |agent| &mut agent.owns_car, rng);
 
-        // Filter agents without cars
-        let agents_without_cars: Vec<&Rc<RefCell<Agent>>> = agents
-            .iter()
-            .filter(|agent| !agent.borrow().owns_car)
-            .collect();
+    applied
+}
 
-        // Choose a random sample, the size of the increase, and give them cars
-        let mut rng = thread_rng();
-        let sample = sample_slice_ref(
-            &mut rng, 
-            &agents_without_cars, 
-            scenario.intervention.change_in_number_of_cars as usize);
-        
-        sample
-            .iter()
-            .for_each(|agent| agent.borrow_mut().owns_car = true);
-    } else if scenario.intervention.change_in_number_of_cars < 0 {
-        // Take away some cars
+/// This adds the part of Intervention.neighbourhood_changes.increase_in_supportiveness that is
+/// due on a day to Neighbourhood.supportiveness, and the same for increase_in_capacity and Neighbourhood.capacity
+/// * intervention: The intervention that is running
+/// * applied: The changes made by the intervention so far, the changes made today are added to this
+/// * day: The day number
+/// * bounds: What to do if supportiveness goes out of bounds
+fn change_neighbourhoods(intervention: &Intervention, applied: &mut AppliedIntervention, day: u32, bounds: Bounds) {
+    // The changes in applied are in the same order as the neighbourhood changes of the intervention
+    for (i, change) in intervention.neighbourhood_changes.iter().enumerate() {
+        if let Some((increase_in_supportiveness, increase_in_capacity)) = change.increase_on_day(day - intervention.start_day) {
+            let (ref neighbourhood, ref mut supportiveness_made) = applied.supportiveness[i];
+            let increase_made = change_supportiveness(neighbourhood, &increase_in_supportiveness, bounds.supportiveness);
+            *supportiveness_made = union_of(supportiveness_made, &increase_made, |v1, v2| v1 + v2);
+
+            let (ref neighbourhood, ref mut capacity_made) = applied.capacity[i];
+            let increase_made = change_capacity(neighbourhood, &increase_in_capacity);
+            *capacity_made = union_of(capacity_made, &increase_made, |v1, v2| v1 + v2);
+        }
+    }
+}
+
+/// This reverts the changes made by an intervention
+/// * applied: The changes made by the intervention
+/// * bounds: What to do if supportiveness goes out of bounds
+fn revert_intervention(applied: AppliedIntervention, bounds: Bounds) {
+    for (neighbourhood, increase) in applied.supportiveness.iter() {
+        change_supportiveness(neighbourhood, &negate(increase), bounds.supportiveness);
+    }
 
-        // Filter agents with cars
-        let agents_with_cars: Vec<&Rc<RefCell<Agent>>> = agents
+    for (neighbourhood, increase) in applied.capacity.iter() {
+        let decrease: HashMap<TransportMode, i64> = increase
             .iter()
-            .filter(|agent| agent.borrow().owns_car)
+            .map(|(&mode, &v)| (mode, -v))
             .collect();
+        change_capacity(neighbourhood, &decrease);
+    }
 
-        // Choose a random sample, the size of the decrease, and take away cars
-        let mut rng = thread_rng();
-        let decrease_in_cars = (scenario.intervention.change_in_number_of_cars * -1) as usize;
-        let sample = sample_slice_ref(
-            &mut rng, 
-            &agents_with_cars, 
-            decrease_in_cars);
-        
-        sample
-            .iter()
-            .for_each(|agent| agent.borrow_mut().owns_car = false);
+    for (subculture, increase) in applied.desirability.iter() {
+        change_desirability(subculture, &negate(increase));
+    }
+
+    for (agent, owned_bike) in applied.bike_owners.into_iter() {
+        agent.borrow_mut().owns_bike = owned_bike;
+    }
+
+    for (agent, owned_car) in applied.car_owners.into_iter() {
+        agent.borrow_mut().owns_car = owned_car;
+    }
+}
+
+/// Negates every value in a HashMap
+/// * hashmap: The HashMap to negate
+/// * Returns: A HashMap of -v for each (k, v) in hashmap
+fn negate(hashmap: &HashMap<TransportMode, f32>) -> HashMap<TransportMode, f32> {
+    hashmap
+        .iter()
+        .map(|(&mode, &v)| (mode, -v))
+        .collect()
+}
+
+/// Adds an increase in supportiveness to a neighbourhood
+/// * neighbourhood: The neighbourhood to change
+/// * increase_in_supportiveness: The increase for each mode
+/// * policy: If Clamp, the supportiveness is kept from 0-1
+/// * Returns: The increase that was made for each mode, once the supportiveness was clamped
+fn change_supportiveness(
+    neighbourhood: &Neighbourhood,
+    increase_in_supportiveness: &HashMap<TransportMode, f32>,
+    policy: BoundsPolicy) -> HashMap<TransportMode, f32>
+{
+    let (new_supportiveness, _) = validation::add_supportiveness(
+        &neighbourhood.supportiveness.borrow(), increase_in_supportiveness, policy);
+
+    // Calculate the increase that was actually made
+    let increase_made: HashMap<TransportMode, f32> = increase_in_supportiveness
+        .keys()
+        .map(|mode| (*mode, new_supportiveness[mode] - neighbourhood.supportiveness.borrow().get(mode).unwrap_or(&0.0)))
+        .collect();
+
+    neighbourhood.supportiveness.replace(new_supportiveness);
+
+    increase_made
+}
+
+/// Adds an increase in capacity to a neighbourhood, keeping the capacity within the bounds of a u32
+/// * neighbourhood: The neighbourhood to change
+/// * increase_in_capacity: The increase for each mode
+/// * Returns: The increase that was made for each mode, once the capacity was kept within bounds
+fn change_capacity(neighbourhood: &Neighbourhood, increase_in_capacity: &HashMap<TransportMode, i64>) -> HashMap<TransportMode, i64> {
+    let (new_capacity, _) = validation::add_capacity(&neighbourhood.capacity.borrow(), increase_in_capacity);
+
+    // Calculate the increase that was actually made
+    let increase_made: HashMap<TransportMode, i64> = increase_in_capacity
+        .keys()
+        .map(|mode| (*mode, i64::from(new_capacity[mode]) - i64::from(*neighbourhood.capacity.borrow().get(mode).unwrap_or(&0))))
+        .collect();
+
+    neighbourhood.capacity.replace(new_capacity);
+
+    increase_made
+}
+
+/// Adds an increase in desirability to a subculture
+/// * subculture: The subculture to change
+/// * increase_in_desirability: The increase for each mode
+fn change_desirability(subculture: &Subculture, increase_in_desirability: &HashMap<TransportMode, f32>) {
+    let new_desirability = union_of(
+        &subculture.desirability.borrow(),
+        increase_in_desirability,
+        |v1, v2| v1 + v2);
+
+    subculture.desirability.replace(new_desirability);
+}
+
+/// Gives (or takes away) bikes or cars from agents chosen from those targeted
+/// * agents: The agents in the simulation
+/// * change: The change in the number owned, positive to give, negative to take away
+/// * target: The agents the change is targeted at
+/// * owns: Gets whether an agent owns a bike or car
+/// * rng: The random number generator
+/// * Returns: The agents whose ownership changed, and whether they owned one before
+fn change_ownership<R: Rng>(
+    agents: &[Rc<RefCell<Agent>>],
+    change: i32,
+    target: &Target,
+    owns: fn(&mut Agent) -> &mut bool,
+    rng: &mut R) -> Vec<(Rc<RefCell<Agent>>, bool)>
+{
+    if change == 0 {
+        return Vec::new();
+    }
+
+    // If giving, filter targeted agents without one, otherwise filter targeted agents with one
+    let giving = change > 0;
+    let candidates: Vec<&Rc<RefCell<Agent>>> = agents
+        .iter()
+        .filter(|agent| *owns(&mut agent.borrow_mut()) != giving && target.matches(&agent.borrow()))
+        .collect();
+
+    let mut amount = change.unsigned_abs() as usize;
+    if amount > candidates.len() {
+        warn!("Only {} agents are targeted by a change of {}, the change will be made to all of them",
+            candidates.len(), change);
+        amount = candidates.len();
     }
+
+    // Choose a random sample, the size of the change, or the most connected candidates
+    let sample = match target.selection {
+        Selection::Random => sample_slice_ref(rng, &candidates, amount),
+        Selection::MostConnected => {
+            // Shuffle first, the sort is stable so ties stay in a random order
+            let mut shuffled = sample_slice_ref(rng, &candidates, candidates.len());
+            shuffled.sort_by_key(|agent| std::cmp::Reverse(agent.borrow().social_network.len()));
+            shuffled.truncate(amount);
+            shuffled
+        }
+    };
+
+    // Give or take away
+    sample
+        .iter()
+        .map(|agent| {
+            *owns(&mut agent.borrow_mut()) = giving;
+            (Rc::clone(agent), !giving)
+        })
+        .collect()
 }
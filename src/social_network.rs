@@ -1,6 +1,6 @@
 use rand::distributions;
 use rand::distributions::Distribution;
-use rand::{thread_rng};
+use rand::Rng;
 use std::collections::HashMap;
 
 /// Generate a scale free network
@@ -8,10 +8,12 @@ use std::collections::HashMap;
 /// * agents: a slice of agents
 /// * min: the minimum number of links
 /// * total_number: the number of nodes in the network
+/// * rng: The random number generator
 /// * Returns: A HashMap mapping ids, to the ids of their friends
-pub fn generate_social_network(min: u32, total_number: u32) -> HashMap<u32, Vec<u32>>{
-    // Create a HashMap mapping ids, to the ids of their friends
-    let mut network: HashMap<u32, Vec<u32>> = HashMap::new();
+pub fn generate_social_network<R: Rng>(min: u32, total_number: u32, rng: &mut R) -> HashMap<u32, Vec<u32>>{
+    // Create a Vec where index i stores the ids of i's friends,
+    // a Vec is used over a HashMap, as it is iterated over in a fixed order
+    let mut network: Vec<Vec<u32>> = Vec::with_capacity(total_number as usize);
 
     // For total_number ids
     for i in 0..total_number {
@@ -19,8 +21,8 @@ pub fn generate_social_network(min: u32, total_number: u32) -> HashMap<u32, Vec<
         let mut i_friends: Vec<u32> = Vec::new();
         // If there aren't yet min number of people in the network, link i to everyone already in the network
         if network.len() < min as usize {
-            for (&k, v) in network.iter_mut() {
-                i_friends.push(k);
+            for (k, v) in network.iter_mut().enumerate() {
+                i_friends.push(k as u32);
                 v.push(i);
             }
         } else {
@@ -28,20 +30,25 @@ pub fn generate_social_network(min: u32, total_number: u32) -> HashMap<u32, Vec<
             // This is preferential attachment
             let mut weighted: Vec<distributions::Weighted<u32>> = network
                 .iter()
-                .map(|(&k, v)| distributions::Weighted {weight: v.len() as u32, item: k})
+                .enumerate()
+                .map(|(k, v)| distributions::Weighted {weight: v.len() as u32, item: k as u32})
                 .collect();
             let weighted_choice = distributions::WeightedChoice::new(&mut weighted);
 
             // For min ids selected from the sample, link them together
             for _ in 0..min {
-                let id: u32 = weighted_choice.sample(&mut thread_rng());
-                network.get_mut(&id).unwrap().push(i);
+                let id: u32 = weighted_choice.sample(rng);
+                network[id as usize].push(i);
                 i_friends.push(id);
             }
         }
         // Add the newly linked id to the network
-        network.insert(i, i_friends);
+        network.push(i_friends);
     }
 
     network
+        .into_iter()
+        .enumerate()
+        .map(|(k, v)| (k as u32, v))
+        .collect()
 }
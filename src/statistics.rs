@@ -6,6 +6,7 @@ use neighbourhood::Neighbourhood;
 use std::rc::Rc;
 use std::cell::RefCell;
 use itertools::Itertools;
+use hashmap_union::union_of;
 
 /// Counts the number of agents who take an active mode
 /// * agents: The agents to count from
@@ -13,11 +14,107 @@ use itertools::Itertools;
 pub fn count_active_mode(agents: &[Rc<RefCell<Agent>>]) -> usize {
     agents
         .iter()
-        .filter(|&a| a.borrow().current_mode == TransportMode::Walk
-            || a.borrow().current_mode == TransportMode::Cycle)
+        .filter(|&a| a.borrow().current_mode.is_active())
         .count()
 }
 
+/// The number of agents whose mode agrees or disagrees with their norm
+pub struct NormCounts {
+    /// The number of agents whose norm is an active mode
+    pub active_norm: usize,
+    /// The number of agents who take an active mode, but whose norm is an inactive mode
+    pub active_mode_counter_to_inactive_norm: usize,
+    /// The number of agents who take an inactive mode, but whose norm is an active mode
+    pub inactive_mode_counter_to_active_norm: usize
+}
+
+/// Calculate the proportion of agents who travel by each mode
+/// * agents: The agents to calculate the proportions from
+/// * mode_of: Gets the mode of an agent, e.g. its current or last mode
+/// * Returns: A Map: TransportMode -> The proportion of agents who travel by that mode
+pub fn calculate_mode_share(agents: &[Rc<RefCell<Agent>>], mode_of: fn(&Agent) -> TransportMode) -> HashMap<TransportMode, f32> {
+    let mut share = HashMap::new();
+
+    if agents.is_empty() {
+        return share;
+    }
+
+    for agent in agents.iter() {
+        *share.entry(mode_of(&agent.borrow())).or_insert(0.0) += 1.0;
+    }
+
+    share.values_mut().for_each(|v| *v /= agents.len() as f32);
+    share
+}
+
+/// Calculate the norm of an agent, from the modes of its friends and neighbours  
+/// This is the share of each mode in the agent's social network and neighbours, weighted
+/// by how connected the agent is to each
+/// * agent: The agent to calculate the norm for
+/// * mode_of: Gets the mode of a friend or neighbour, e.g. its current or last mode
+/// * Returns: A Map: TransportMode -> The norm from 0-1 of that mode
+pub fn calculate_norm(agent: &Agent, mode_of: fn(&Agent) -> TransportMode) -> HashMap<TransportMode, f32> {
+    let total_connectivity = agent.social_connectivity + agent.neighbourhood_connectivity;
+
+    if total_connectivity <= 0.0 {
+        return HashMap::new();
+    }
+
+    let social_norm: HashMap<TransportMode, f32> = calculate_mode_share(&agent.social_network, mode_of)
+        .into_iter()
+        .map(|(mode, v)| (mode, v * agent.social_connectivity / total_connectivity))
+        .collect();
+
+    let neighbourhood_norm: HashMap<TransportMode, f32> = calculate_mode_share(&agent.neighbours, mode_of)
+        .into_iter()
+        .map(|(mode, v)| (mode, v * agent.neighbourhood_connectivity / total_connectivity))
+        .collect();
+
+    union_of(&social_norm, &neighbourhood_norm, |v1, v2| v1 + v2)
+}
+
+/// Whether the norm of an agent is an active mode, from the current modes of its friends and neighbours
+/// * agent: The agent
+/// * Returns: true iff more than half of the agent's norm is Walk or Cycle
+pub fn has_active_norm(agent: &Agent) -> bool {
+    let active_norm: f32 = calculate_norm(agent, |a| a.current_mode)
+        .iter()
+        .filter(|(mode, _)| mode.is_active())
+        .map(|(_, v)| v)
+        .sum();
+
+    active_norm > 0.5
+}
+
+/// Counts the number of agents whose current mode agrees or disagrees with their norm
+/// * agents: The agents to count from
+/// * Returns: The counts, see NormCounts
+pub fn count_norms(agents: &[Rc<RefCell<Agent>>]) -> NormCounts {
+    let mut counts = NormCounts {
+        active_norm: 0,
+        active_mode_counter_to_inactive_norm: 0,
+        inactive_mode_counter_to_active_norm: 0
+    };
+
+    for agent in agents.iter() {
+        let agent = agent.borrow();
+        let active_norm = has_active_norm(&agent);
+        let active_mode = agent.current_mode.is_active();
+
+        if active_norm {
+            counts.active_norm += 1;
+        }
+
+        if active_mode && !active_norm {
+            counts.active_mode_counter_to_inactive_norm += 1;
+        } else if !active_mode && active_norm {
+            counts.inactive_mode_counter_to_active_norm += 1;
+        }
+    }
+
+    counts
+}
+
 /// Counts the number of agents who take an active mode grouped by commute length
 /// * agents: The agents to count from
 /// * Returns: A Map: JourneyType -> The number of agent's who's current mode is Walk or Cycle 
@@ -31,12 +128,37 @@ pub fn count_active_mode_by_commute_length(agents: &[Rc<RefCell<Agent>>]) -> Has
         .collect()
 }
 
+/// Counts the number of agents who take an active mode grouped by subculture
+/// * agents: The agents to count from
+/// * Returns: A Map: Subculture ID -> The number of agent's who's current mode is Walk or Cycle 
+pub fn count_active_mode_by_subculture(agents: &[Rc<RefCell<Agent>>]) -> HashMap<String, usize> {
+    agents
+        .iter()
+        .map(|agent| (agent.borrow().subculture_id.clone(), Rc::clone(agent)))
+        .into_group_map()
+        .into_iter()
+        .map(|(subculture_id, grouped_agents)| (subculture_id, count_active_mode(&grouped_agents)))
+        .collect()
+}
+
 /// Counts the number of agents who take an active mode grouped by neighbourhood
 /// * neighbourhoods: The neighbourhoods to count from
-/// * Returns: A Map: Neighbourhood -> The number of agent's who's current mode is Walk or Cycle 
-pub fn count_active_mode_by_neighbourhood (neighbourhoods: &[Rc<Neighbourhood>]) -> HashMap<Rc<Neighbourhood>, usize> {
+/// * Returns: A Map: Neighbourhood ID -> The number of agent's who's current mode is Walk or Cycle 
+pub fn count_active_mode_by_neighbourhood (neighbourhoods: &[Rc<Neighbourhood>]) -> HashMap<String, usize> {
     neighbourhoods
         .iter()
-        .map(|neighbourhood| (Rc::clone(neighbourhood), count_active_mode(&neighbourhood.residents.borrow())))
+        .map(|neighbourhood| (neighbourhood.id.clone(), count_active_mode(&neighbourhood.residents.borrow())))
         .collect()
 }
+
+/// Counts the number of agents who take each mode grouped by neighbourhood and commute length
+/// * agents: The agents to count from
+/// * Returns: A Map: (Neighbourhood ID, JourneyType, TransportMode) -> The number of agents whose current mode is the mode
+pub fn count_modes_by_neighbourhood_and_commute_length(agents: &[Rc<RefCell<Agent>>]) -> HashMap<(String, JourneyType, TransportMode), usize> {
+    let mut counts = HashMap::new();
+    for agent in agents.iter() {
+        let agent = agent.borrow();
+        *counts.entry((agent.neighbourhood_id.clone(), agent.commute_length, agent.current_mode)).or_insert(0) += 1;
+    }
+    counts
+}
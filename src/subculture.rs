@@ -0,0 +1,39 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::cell::RefCell;
+use transport_mode::TransportMode;
+
+/// A Subculture, a group of agents who share views on how desirable each transport mode is
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Subculture {
+    /// The ID for the Subculture, subcultures are equal if they share the same id
+    pub id: String,
+    /// A score from 0-1 for each transport mode, on how desirable the subculture finds it
+    pub desirability: RefCell<HashMap<TransportMode, f32>>
+}
+
+impl Default for Subculture {
+    fn default() -> Self {
+        Subculture {
+            id: "placeholder".to_string(),
+            desirability: RefCell::new(HashMap::new())
+        }
+    }
+}
+
+impl PartialEq for Subculture {
+    /// Tests equality of subcultures, only equal if they have the same id
+    fn eq(&self, other: &Subculture) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for Subculture {}
+
+impl Hash for Subculture {
+    /// Returns the hash of the id
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
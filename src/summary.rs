@@ -0,0 +1,167 @@
+use std::fs;
+use std::fs::File;
+use std::io::BufRead;
+use std::io::BufReader;
+use error::Error;
+use error::Problem;
+
+/// The active mode count at the start and end of a simulation
+pub struct SimulationSummary {
+    /// The ID of the simulation
+    pub id: u32,
+    /// The number of agents using an active mode on day 0
+    pub initial_active_mode: usize,
+    /// The last day written to the output
+    pub final_day: u32,
+    /// The number of agents using an active mode on the last day
+    pub final_active_mode: usize
+}
+
+/// Finds the outputs of the simulations in a directory
+/// * output_directory: The directory containing output_n.csv files
+/// * Returns: The ID and path of each output, in order of ID
+pub fn find_outputs(output_directory: &str) -> Result<Vec<(u32, String)>, Error> {
+    let mut outputs: Vec<(u32, String)> = fs::read_dir(output_directory)?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            let id = name.strip_prefix("output_")?.strip_suffix(".csv")?.parse::<u32>().ok()?;
+            Some((id, entry.path().to_string_lossy().into_owned()))
+        })
+        .collect();
+
+    outputs.sort();
+    Ok(outputs)
+}
+
+/// Reads the rows of a simulation's output
+/// * path: The path of the output CSV file
+/// * Returns: The header, and each row, split into columns
+pub fn read_output(path: &str) -> Result<(Vec<String>, Vec<Vec<String>>), Error> {
+    let mut lines = BufReader::new(File::open(path)?).lines();
+
+    // Each line ends with a trailing comma, so the last column is empty
+    let split = |line: String| -> Vec<String> {
+        line.trim_end_matches(',').split(',').map(|column| column.to_string()).collect()
+    };
+
+    let header = match lines.next() {
+        Some(line) => split(line?),
+        None => return Err(Error::Invalid(vec![Problem::new(path, "", "the output is empty")]))
+    };
+
+    let mut rows = Vec::new();
+    for line in lines {
+        rows.push(split(line?));
+    }
+
+    Ok((header, rows))
+}
+
+/// Finds a column of an output
+/// * header: The header of the output
+/// * column: The name of the column
+/// * path: The path of the output, for reporting problems
+/// * Returns: The index of the column
+pub fn find_column(header: &[String], column: &str, path: &str) -> Result<usize, Error> {
+    header
+        .iter()
+        .position(|name| name == column)
+        .ok_or_else(|| Error::Invalid(vec![Problem::new(path, "", format!("there is no {} column", column))]))
+}
+
+/// Summarises the output of a simulation
+/// * id: The ID of the simulation
+/// * path: The path of the output CSV file
+/// * Returns: The active mode count at the start and end of the simulation
+fn summarise_output(id: u32, path: &str) -> Result<SimulationSummary, Error> {
+    let (header, rows) = read_output(path)?;
+    let day_column = find_column(&header, "Day", path)?;
+    let active_mode_column = find_column(&header, "ActiveMode", path)?;
+
+    let invalid = |message: &str| Error::Invalid(vec![Problem::new(path, "", message)]);
+    let parse = |row: &Vec<String>, column: usize| -> Result<usize, Error> {
+        row.get(column)
+            .and_then(|value| value.parse::<usize>().ok())
+            .ok_or_else(|| invalid("a row is not a number"))
+    };
+
+    let first_row = rows.first().ok_or_else(|| invalid("the output has no rows"))?;
+    let last_row = rows.last().ok_or_else(|| invalid("the output has no rows"))?;
+
+    Ok(SimulationSummary {
+        id,
+        initial_active_mode: parse(first_row, active_mode_column)?,
+        final_day: parse(last_row, day_column)? as u32,
+        final_active_mode: parse(last_row, active_mode_column)?
+    })
+}
+
+/// Summarises the output of every simulation in a directory
+/// * output_directory: The directory containing output_n.csv files
+/// * number_of_people: The number of people in each simulation, used for the active mode share
+/// * Returns: A table of the active mode share at the start and end of each simulation, and the mean
+pub fn summarise(output_directory: &str, number_of_people: u32) -> Result<String, Error> {
+    let summaries: Vec<SimulationSummary> = find_outputs(output_directory)?
+        .iter()
+        .map(|&(id, ref path)| summarise_output(id, path))
+        .collect::<Result<Vec<SimulationSummary>, Error>>()?;
+
+    if summaries.is_empty() {
+        return Err(Error::Invalid(vec![Problem::new(output_directory, "", "there are no outputs to summarise")]));
+    }
+
+    let share = |count: usize| 100.0 * count as f64 / f64::from(number_of_people.max(1));
+
+    let mut table = String::from("Simulation,FinalDay,InitialActiveMode,FinalActiveMode,InitialActiveShare(%),FinalActiveShare(%)\n");
+    for summary in summaries.iter() {
+        table.push_str(&format!("{},{},{},{},{:.2},{:.2}\n",
+            summary.id,
+            summary.final_day,
+            summary.initial_active_mode,
+            summary.final_active_mode,
+            share(summary.initial_active_mode),
+            share(summary.final_active_mode)));
+    }
+
+    let mean = |count_of: fn(&SimulationSummary) -> usize| {
+        summaries.iter().map(|summary| share(count_of(summary))).sum::<f64>() / summaries.len() as f64
+    };
+    table.push_str(&format!("Mean,,,,{:.2},{:.2}\n",
+        mean(|summary| summary.initial_active_mode),
+        mean(|summary| summary.final_active_mode)));
+
+    Ok(table)
+}
+
+/// The active mode share, averaged over the final year of a simulation
+/// * path: The path of the output CSV file
+/// * number_of_people: The number of people in the simulation
+/// * Returns: The mean share of people using an active mode, from 0-1, over the days in the final 365
+pub fn final_year_active_share(path: &str, number_of_people: u32) -> Result<f64, Error> {
+    let (header, rows) = read_output(path)?;
+    let day_column = find_column(&header, "Day", path)?;
+    let active_mode_column = find_column(&header, "ActiveMode", path)?;
+
+    let parse = |row: &Vec<String>, column: usize| -> Result<f64, Error> {
+        row.get(column)
+            .and_then(|value| value.parse::<f64>().ok())
+            .ok_or_else(|| Error::Invalid(vec![Problem::new(path, "", "a row is not a number")]))
+    };
+
+    let final_day = match rows.last() {
+        Some(row) => parse(row, day_column)?,
+        None => return Err(Error::Invalid(vec![Problem::new(path, "", "the output has no rows")]))
+    };
+
+    let mut total = 0.0;
+    let mut days = 0;
+    for row in rows.iter() {
+        if parse(row, day_column)? > final_day - 365.0 {
+            total += parse(row, active_mode_column)?;
+            days += 1;
+        }
+    }
+
+    Ok(total / days as f64 / f64::from(number_of_people.max(1)))
+}
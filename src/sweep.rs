@@ -0,0 +1,359 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::prelude::*;
+use rand::Rng;
+use serde_yaml;
+use comparison::mean_and_sd;
+use error::Error;
+use error::Problem;
+use parameters::Parameters;
+use rng;
+use rng::Stream;
+
+/// The parameters that can be swept, and the ranges and design used to sample them
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Sweep {
+    /// How the points are chosen
+    pub design: Design,
+
+    /// The range of each parameter that is swept, the other parameters are not changed
+    pub ranges: Vec<Range>,
+
+    /// The number of values of each parameter in a full factorial design
+    #[serde(default = "default_levels")]
+    pub levels: u32,
+
+    /// The number of points of a Latin hypercube design, or the number of base samples of a Saltelli design
+    #[serde(default = "default_samples")]
+    pub samples: u32
+}
+
+/// The default number of values of each parameter in a full factorial design
+fn default_levels() -> u32 {
+    3
+}
+
+/// The default number of samples
+fn default_samples() -> u32 {
+    16
+}
+
+/// How the points of a sweep are chosen
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Design {
+    /// Every combination of evenly spaced values of each parameter, levels ^ parameters points
+    FullFactorial,
+    /// A random sample with one point in each of samples evenly sized intervals of each parameter
+    LatinHypercube,
+    /// Saltelli's scheme using a Sobol sequence, samples * (parameters + 2) points,
+    /// from which first-order and total-order Sobol indices are estimated
+    Saltelli
+}
+
+/// The range of a parameter that is swept
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Range {
+    /// The parameter
+    pub parameter: SweepParameter,
+    /// The smallest value
+    pub minimum: f64,
+    /// The largest value
+    pub maximum: f64
+}
+
+/// A parameter that can be swept, named as in the parameters file
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SweepParameter {
+    SocialConnectivity,
+    SubcultureConnectivity,
+    NeighbourhoodConnectivity,
+    NumberOfSocialNetworkLinks,
+    NumberOfNeighbourLinks,
+    DaysInHabitAverage
+}
+
+/// The (degree, coefficients, initial direction numbers) of dimensions 2-12 of the Sobol sequence,
+/// from Joe and Kuo (new-joe-kuo-6.21201), dimension 1 is the van der Corput sequence,
+/// a Saltelli design needs two dimensions for each parameter
+const SOBOL_DIRECTIONS: [(u32, u32, &[u32]); 11] = [
+    (1, 0, &[1]),
+    (2, 1, &[1, 3]),
+    (3, 1, &[1, 3, 1]),
+    (3, 2, &[1, 1, 1]),
+    (4, 1, &[1, 1, 3, 3]),
+    (4, 4, &[1, 3, 5, 13]),
+    (5, 2, &[1, 1, 5, 5, 17]),
+    (5, 4, &[1, 1, 5, 5, 5]),
+    (5, 7, &[1, 1, 7, 11, 19]),
+    (5, 11, &[1, 1, 5, 1, 1]),
+    (5, 13, &[1, 1, 1, 3, 11])
+];
+
+impl Sweep {
+    /// Loads a sweep from a file
+    /// * file: A YAML file containing the Sweep
+    /// * Returns: The sweep, or an Error if the file could not be read or parsed
+    pub fn from_file(mut file: File) -> Result<Self, Error> {
+        let mut file_contents = String::new();
+        file.read_to_string(&mut file_contents)?;
+        Ok(serde_yaml::from_slice(file_contents.as_bytes())?)
+    }
+
+    /// Checks the sweep, the parameters of each point are checked before the sweep is run
+    /// * file: The file the sweep was loaded from
+    /// * Returns: Every problem found
+    pub fn check(&self, file: &str) -> Vec<Problem> {
+        let mut problems = Vec::new();
+
+        check_ranges(&self.ranges, file, &mut problems);
+
+        if self.design == Design::FullFactorial && self.levels < 2 {
+            problems.push(Problem::new(file, "levels", "should be at least 2"));
+        }
+        if self.design != Design::FullFactorial && self.samples == 0 {
+            problems.push(Problem::new(file, "samples", "should be at least 1"));
+        }
+
+        problems
+    }
+
+    /// The points of the sweep, in the unit hypercube
+    /// * seed: The master seed, a Latin hypercube draws from its own stream
+    /// * Returns: Each point, with a value from 0-1 for each range
+    pub fn unit_points(&self, seed: u64) -> Vec<Vec<f64>> {
+        let dimensions = self.ranges.len();
+        match self.design {
+            Design::FullFactorial => {
+                let levels = self.levels as usize;
+                (0..levels.pow(dimensions as u32))
+                    .map(|point| (0..dimensions)
+                        .map(|dimension| (point / levels.pow(dimension as u32) % levels) as f64 / (levels - 1) as f64)
+                        .collect())
+                    .collect()
+            },
+            Design::LatinHypercube => latin_hypercube(dimensions, self.samples as usize, seed),
+            Design::Saltelli => {
+                // Each base sample gives A, then A with column i from B for each i, then B,
+                // sobol_sequence has already skipped (0, ..., 0), its next point (0.5, ..., 0.5)
+                // is skipped here too, as its A and B are the same
+                let mut points = Vec::new();
+                for sample in sobol_sequence(2 * dimensions, self.samples as usize + 1).into_iter().skip(1) {
+                    let (a, b) = sample.split_at(dimensions);
+                    points.push(a.to_vec());
+                    for i in 0..dimensions {
+                        let mut a_b = a.to_vec();
+                        a_b[i] = b[i];
+                        points.push(a_b);
+                    }
+                    points.push(b.to_vec());
+                }
+                points
+            }
+        }
+    }
+}
+
+/// Checks the ranges of the parameters swept
+/// * ranges: The ranges
+/// * file: The file the ranges were loaded from
+/// * problems: The problems found so far, new problems are added to this
+pub fn check_ranges(ranges: &[Range], file: &str, problems: &mut Vec<Problem>) {
+    if ranges.is_empty() {
+        problems.push(Problem::new(file, "ranges", "at least one parameter is needed"));
+    }
+
+    let mut parameters = HashSet::new();
+    for (i, range) in ranges.iter().enumerate() {
+        if !parameters.insert(range.parameter) {
+            problems.push(Problem::new(file, format!("ranges[{}].parameter", i),
+                format!("{} is used more than once", range.parameter.name())));
+        }
+        if range.minimum > range.maximum {
+            problems.push(Problem::new(file, format!("ranges[{}]", i), "minimum should not be more than maximum"));
+        }
+        if range.parameter.is_whole() && range.minimum < 0.0 {
+            problems.push(Problem::new(file, format!("ranges[{}].minimum", i), "should not be negative"));
+        }
+    }
+}
+
+/// A Latin hypercube sample of the unit hypercube, with one point in each of samples equal intervals of each dimension
+/// * dimensions: The number of dimensions
+/// * samples: The number of points
+/// * seed: The master seed, the points are drawn from the sweep stream
+/// * Returns: The points
+pub fn latin_hypercube(dimensions: usize, samples: usize, seed: u64) -> Vec<Vec<f64>> {
+    let mut rng = rng::create_rng(seed, 0, Stream::Sweep);
+    let mut points = vec![Vec::with_capacity(dimensions); samples];
+    for _ in 0..dimensions {
+        let mut intervals: Vec<usize> = (0..samples).collect();
+        rng.shuffle(&mut intervals);
+        for (point, interval) in points.iter_mut().zip(intervals) {
+            point.push((interval as f64 + rng.gen::<f64>()) / samples as f64);
+        }
+    }
+    points
+}
+
+/// The parameters at a point
+/// * ranges: The ranges of the parameters swept
+/// * base: The parameters that are not swept
+/// * unit_point: The point, with a value from 0-1 for each range
+/// * Returns: The parameters, whole parameters are split into equal intervals for each value
+pub fn parameters_at(ranges: &[Range], base: &Parameters, unit_point: &[f64]) -> Parameters {
+    let mut parameters = base.clone();
+    for (range, &u) in ranges.iter().zip(unit_point.iter()) {
+        let value = range.minimum + u * (range.maximum - range.minimum);
+        let whole = || (range.minimum + u * (range.maximum - range.minimum + 1.0)).floor().min(range.maximum) as u32;
+        match range.parameter {
+            SweepParameter::SocialConnectivity => parameters.social_connectivity = value as f32,
+            SweepParameter::SubcultureConnectivity => parameters.subculture_connectivity = value as f32,
+            SweepParameter::NeighbourhoodConnectivity => parameters.neighbourhood_connectivity = value as f32,
+            SweepParameter::NumberOfSocialNetworkLinks => parameters.number_of_social_network_links = whole(),
+            SweepParameter::NumberOfNeighbourLinks => parameters.number_of_neighbour_links = whole(),
+            SweepParameter::DaysInHabitAverage => parameters.days_in_habit_average = whole()
+        }
+    }
+    parameters
+}
+
+impl SweepParameter {
+    /// The name of the parameter, as in the parameters file
+    pub fn name(self) -> &'static str {
+        match self {
+            SweepParameter::SocialConnectivity => "social_connectivity",
+            SweepParameter::SubcultureConnectivity => "subculture_connectivity",
+            SweepParameter::NeighbourhoodConnectivity => "neighbourhood_connectivity",
+            SweepParameter::NumberOfSocialNetworkLinks => "number_of_social_network_links",
+            SweepParameter::NumberOfNeighbourLinks => "number_of_neighbour_links",
+            SweepParameter::DaysInHabitAverage => "days_in_habit_average"
+        }
+    }
+
+    /// Whether the parameter is a whole number
+    pub fn is_whole(self) -> bool {
+        !matches!(self,
+            SweepParameter::SocialConnectivity | SweepParameter::SubcultureConnectivity | SweepParameter::NeighbourhoodConnectivity)
+    }
+
+    /// The value of the parameter
+    /// * parameters: The parameters
+    /// * Returns: The value of this parameter
+    pub fn value(self, parameters: &Parameters) -> f64 {
+        match self {
+            SweepParameter::SocialConnectivity => f64::from(parameters.social_connectivity),
+            SweepParameter::SubcultureConnectivity => f64::from(parameters.subculture_connectivity),
+            SweepParameter::NeighbourhoodConnectivity => f64::from(parameters.neighbourhood_connectivity),
+            SweepParameter::NumberOfSocialNetworkLinks => f64::from(parameters.number_of_social_network_links),
+            SweepParameter::NumberOfNeighbourLinks => f64::from(parameters.number_of_neighbour_links),
+            SweepParameter::DaysInHabitAverage => f64::from(parameters.days_in_habit_average)
+        }
+    }
+}
+
+/// The points of a Sobol sequence, the first point (0, ..., 0) is skipped
+/// * dimensions: The number of dimensions, at most 12
+/// * number_of_points: The number of points
+/// * Returns: The points, in the unit hypercube
+fn sobol_sequence(dimensions: usize, number_of_points: usize) -> Vec<Vec<f64>> {
+    const BITS: usize = 32;
+
+    // The direction numbers of each dimension, shifted to the top of a u32
+    let directions: Vec<Vec<u32>> = (0..dimensions)
+        .map(|dimension| {
+            let mut v = vec![0u32; BITS];
+            if dimension == 0 {
+                for (k, v_k) in v.iter_mut().enumerate() {
+                    *v_k = 1 << (BITS - 1 - k);
+                }
+            } else {
+                let (degree, coefficients, m) = SOBOL_DIRECTIONS[dimension - 1];
+                let degree = degree as usize;
+                for k in 0..BITS {
+                    v[k] = if k < degree {
+                        m[k] << (BITS - 1 - k)
+                    } else {
+                        let mut v_k = v[k - degree] ^ (v[k - degree] >> degree);
+                        for i in 1..degree {
+                            if (coefficients >> (degree - 1 - i)) & 1 == 1 {
+                                v_k ^= v[k - i];
+                            }
+                        }
+                        v_k
+                    };
+                }
+            }
+            v
+        })
+        .collect();
+
+    // Gray code order, each point differs from the last by the direction number of the lowest zero bit
+    let mut x = vec![0u32; dimensions];
+    (0..number_of_points)
+        .map(|n| {
+            let bit = (!n).trailing_zeros() as usize;
+            for (x_d, v) in x.iter_mut().zip(directions.iter()) {
+                *x_d ^= v[bit];
+            }
+            x.iter().map(|&x_d| f64::from(x_d) / 2f64.powi(BITS as i32)).collect()
+        })
+        .collect()
+}
+
+/// The first-order and total-order Sobol indices of each parameter of a Saltelli design  
+/// Uses the estimators of Saltelli et al. (2010) for the first-order index, and Jansen (1999) for the total-order index
+/// * dimensions: The number of parameters swept
+/// * outputs: The output at each point of the design, in the order of the points
+/// * Returns: The first-order and total-order index of each parameter
+pub fn sobol_indices(dimensions: usize, outputs: &[f64]) -> Vec<(f64, f64)> {
+    let blocks: Vec<&[f64]> = outputs.chunks(dimensions + 2).collect();
+    let f_a: Vec<f64> = blocks.iter().map(|block| block[0]).collect();
+    let f_b: Vec<f64> = blocks.iter().map(|block| block[dimensions + 1]).collect();
+
+    let (_, sd) = mean_and_sd(&f_a.iter().chain(f_b.iter()).cloned().collect::<Vec<f64>>());
+    let variance = sd * sd;
+    let n = blocks.len() as f64;
+
+    (0..dimensions)
+        .map(|i| {
+            let f_a_b: Vec<f64> = blocks.iter().map(|block| block[i + 1]).collect();
+            let first_order = (0..blocks.len()).map(|j| f_b[j] * (f_a_b[j] - f_a[j])).sum::<f64>() / n / variance;
+            let total_order = (0..blocks.len()).map(|j| (f_a[j] - f_a_b[j]).powi(2)).sum::<f64>() / (2.0 * n) / variance;
+            (first_order, total_order)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sobol_sequence_skips_the_origin() {
+        let points = sobol_sequence(2, 4);
+        assert_eq!(points, vec![vec![0.5, 0.5], vec![0.75, 0.25], vec![0.25, 0.75], vec![0.375, 0.375]]);
+    }
+
+    #[test]
+    fn sobol_indices_of_a_linear_additive_model() {
+        // y = 2 x1 + x2 + 0 x3, with x uniform on 0-1, the variance of each term is its coefficient squared / 12,
+        // so the first-order and total-order indices are 0.8, 0.2 and 0
+        let sweep = Sweep {
+            design: Design::Saltelli,
+            ranges: [SweepParameter::SocialConnectivity, SweepParameter::SubcultureConnectivity, SweepParameter::NeighbourhoodConnectivity]
+                .iter()
+                .map(|&parameter| Range { parameter, minimum: 0.0, maximum: 1.0 })
+                .collect(),
+            levels: default_levels(),
+            samples: 4096
+        };
+        let outputs: Vec<f64> = sweep.unit_points(1).iter().map(|x| 2.0 * x[0] + x[1]).collect();
+
+        let expected = [0.8, 0.2, 0.0];
+        for (&(first_order, total_order), expected) in sobol_indices(3, &outputs).iter().zip(expected.iter()) {
+            assert!((first_order - expected).abs() < 0.02, "first-order {} should be {}", first_order, expected);
+            assert!((total_order - expected).abs() < 0.02, "total-order {} should be {}", total_order, expected);
+        }
+    }
+}
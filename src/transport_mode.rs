@@ -1,8 +1,21 @@
 /// The Transport Modes that can be taken by agents
-#[derive(Eq, Hash, PartialEq, Copy, Clone, Serialize, Deserialize)]
+#[derive(Debug, Eq, Hash, PartialEq, Copy, Clone, Serialize, Deserialize)]
 pub enum TransportMode {
     Car,
     PublicTransport,
     Cycle,
     Walk
 }
+
+impl TransportMode {
+    /// Every transport mode, in a fixed order  
+    /// This should be used where the order of iteration matters, as HashMap order is not fixed
+    pub fn all() -> [TransportMode; 4] {
+        [TransportMode::Car, TransportMode::PublicTransport, TransportMode::Cycle, TransportMode::Walk]
+    }
+
+    /// Returns true iff the mode is an active mode (Walk or Cycle)
+    pub fn is_active(self) -> bool {
+        self == TransportMode::Walk || self == TransportMode::Cycle
+    }
+}
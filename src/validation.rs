@@ -0,0 +1,307 @@
+use std::collections::HashMap;
+use std::fmt;
+use hashmap_union::union_of;
+use transport_mode::TransportMode;
+use scenario::Scenario;
+use error::Problem;
+
+/// What to do when an intervention takes a value out of its bounds
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum BoundsPolicy {
+    /// The scenario is rejected when it is loaded
+    Error,
+    /// The value is kept within its bounds when the intervention is applied
+    Clamp,
+    /// A warning is logged when the scenario is loaded, and the value is left out of bounds
+    Warn
+}
+
+/// The policy for each field an intervention can take out of bounds
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct Bounds {
+    /// Supportiveness should be from 0-1
+    #[serde(default = "default_supportiveness_policy")]
+    pub supportiveness: BoundsPolicy,
+
+    /// Capacity should be from 0 to the max size of u32
+    /// Capacity is stored as a u32, so it is always clamped, Warn only logs a warning
+    #[serde(default = "default_capacity_policy")]
+    pub capacity: BoundsPolicy
+}
+
+impl Default for Bounds {
+    fn default() -> Self {
+        Bounds {
+            supportiveness: default_supportiveness_policy(),
+            capacity: default_capacity_policy()
+        }
+    }
+}
+
+/// By default out of bounds supportiveness is warned about
+fn default_supportiveness_policy() -> BoundsPolicy {
+    BoundsPolicy::Warn
+}
+
+/// By default out of bounds capacity is clamped
+fn default_capacity_policy() -> BoundsPolicy {
+    BoundsPolicy::Clamp
+}
+
+/// A field of a neighbourhood that an intervention changes
+#[derive(Clone, Copy, PartialEq)]
+pub enum Field {
+    Supportiveness,
+    Capacity
+}
+
+/// A value taken out of bounds by the intervention schedule
+pub struct Violation {
+    /// The day on which the value went out of bounds
+    pub day: u32,
+    /// The ID of the neighbourhood
+    pub neighbourhood_id: String,
+    /// The transport mode
+    pub mode: TransportMode,
+    /// The field that is out of bounds
+    pub field: Field,
+    /// The value the intervention would make it
+    pub value: f64
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (field, bounds) = match self.field {
+            Field::Supportiveness => ("supportiveness", "0 - 1"),
+            Field::Capacity => ("capacity", "0 - 4294967295")
+        };
+        write!(f, "day {}: neighbourhood {} {:?} {} would be {}, outside {}",
+            self.day, self.neighbourhood_id, self.mode, field, self.value, bounds)
+    }
+}
+
+/// Adds an increase in supportiveness to a supportiveness
+/// * supportiveness: The current supportiveness
+/// * increase_in_supportiveness: The increase for each mode
+/// * policy: If Clamp, the new supportiveness is kept from 0-1
+/// * Returns: The new supportiveness, and the modes taken out of bounds with their unclamped value
+pub fn add_supportiveness(
+    supportiveness: &HashMap<TransportMode, f32>,
+    increase_in_supportiveness: &HashMap<TransportMode, f32>,
+    policy: BoundsPolicy) -> (HashMap<TransportMode, f32>, Vec<(TransportMode, f64)>)
+{
+    let mut new_supportiveness = union_of(supportiveness, increase_in_supportiveness, |v1, v2| v1 + v2);
+
+    let mut out_of_bounds = Vec::new();
+    for mode in TransportMode::all().iter().filter(|mode| increase_in_supportiveness.contains_key(mode)) {
+        let value = new_supportiveness[mode];
+        if !(0.0..=1.0).contains(&value) {
+            out_of_bounds.push((*mode, f64::from(value)));
+            if policy == BoundsPolicy::Clamp {
+                new_supportiveness.insert(*mode, value.clamp(0.0, 1.0));
+            }
+        }
+    }
+
+    (new_supportiveness, out_of_bounds)
+}
+
+/// Adds an increase in capacity to a capacity, keeping the capacity within the bounds of a u32
+/// * capacity: The current capacity
+/// * increase_in_capacity: The increase for each mode
+/// * Returns: The new capacity, and the modes taken out of bounds with their unclamped value
+pub fn add_capacity(
+    capacity: &HashMap<TransportMode, u32>,
+    increase_in_capacity: &HashMap<TransportMode, i64>) -> (HashMap<TransportMode, u32>, Vec<(TransportMode, f64)>)
+{
+    // Convert capacity for an u32 to i64 (i64 has a higher max value than u32, which has a higher max than i32)
+    let signed_capacity: HashMap<TransportMode, i64> = capacity
+        .iter()
+        .map(|(&k, &v)| (k, i64::from(v)))
+        .collect();
+
+    let new_capacity = union_of(&signed_capacity, increase_in_capacity, |v1, v2| v1 + v2);
+
+    let out_of_bounds = TransportMode::all()
+        .iter()
+        .filter(|mode| increase_in_capacity.contains_key(mode))
+        .map(|mode| (*mode, new_capacity[mode]))
+        .filter(|&(_, value)| value < 0 || value > i64::from(u32::MAX))
+        .map(|(mode, value)| (mode, value as f64))
+        .collect();
+
+    // Convert back to u32, keeping within its bounds
+    let new_capacity = new_capacity
+        .into_iter()
+        .map(|(k, v)| (k, v.clamp(0, i64::from(u32::MAX)) as u32))
+        .collect();
+
+    (new_capacity, out_of_bounds)
+}
+
+/// The changes made to a neighbourhood by a NeighbourhoodChange of a running intervention
+struct ChangeMade {
+    supportiveness: HashMap<TransportMode, f32>,
+    capacity: HashMap<TransportMode, i64>
+}
+
+/// This simulates the intervention schedule of a scenario, in the same way as the simulation,
+/// and finds every time supportiveness or capacity goes out of bounds
+/// * scenario: The scenario to validate
+/// * Returns: The violations, in order of day
+pub fn find_violations(scenario: &Scenario) -> Vec<Violation> {
+    let bounds = scenario.bounds;
+
+    // Copy the supportiveness and capacity, so the scenario is not changed
+    let mut supportiveness: HashMap<String, HashMap<TransportMode, f32>> = scenario
+        .neighbourhoods
+        .iter()
+        .map(|neighbourhood| (neighbourhood.id.clone(), neighbourhood.supportiveness.borrow().clone()))
+        .collect();
+    let mut capacity: HashMap<String, HashMap<TransportMode, u32>> = scenario
+        .neighbourhoods
+        .iter()
+        .map(|neighbourhood| (neighbourhood.id.clone(), neighbourhood.capacity.borrow().clone()))
+        .collect();
+
+    // After this day, no intervention changes anything
+    let last_day = scenario
+        .interventions
+        .iter()
+        .map(|intervention| {
+            let last_ramp_day = intervention
+                .neighbourhood_changes
+                .iter()
+                .map(|change| intervention.start_day + change.ramp.as_ref().map_or(0, |ramp| ramp.days))
+                .max()
+                .unwrap_or(intervention.start_day);
+            last_ramp_day.max(intervention.end_day.unwrap_or(0))
+        })
+        .max()
+        .unwrap_or(0);
+
+    let mut changes_made: Vec<Option<Vec<ChangeMade>>> = scenario.interventions.iter().map(|_| None).collect();
+    let mut violations = Vec::new();
+
+    for day in 1..=last_day {
+        // Revert the interventions that end today
+        for (intervention, made) in scenario.interventions.iter().zip(changes_made.iter_mut()) {
+            if intervention.end_day != Some(day) {
+                continue;
+            }
+            if let Some(made) = made.take() {
+                for (change, made) in intervention.neighbourhood_changes.iter().zip(made.iter()) {
+                    let decrease: HashMap<TransportMode, f32> = made.supportiveness.iter().map(|(&k, &v)| (k, -v)).collect();
+                    let decrease_in_capacity: HashMap<TransportMode, i64> = made.capacity.iter().map(|(&k, &v)| (k, -v)).collect();
+                    apply_change(&change.id, day, &decrease, &decrease_in_capacity, bounds,
+                        &mut supportiveness, &mut capacity, &mut violations);
+                }
+            }
+        }
+
+        // Start the interventions that start today
+        for (intervention, made) in scenario.interventions.iter().zip(changes_made.iter_mut()) {
+            if intervention.start_day == day {
+                *made = Some(intervention
+                    .neighbourhood_changes
+                    .iter()
+                    .map(|_| ChangeMade { supportiveness: HashMap::new(), capacity: HashMap::new() })
+                    .collect());
+            }
+        }
+
+        // Make today's part of the changes of the running interventions
+        for (intervention, made) in scenario.interventions.iter().zip(changes_made.iter_mut()) {
+            if let Some(ref mut made) = *made {
+                for (change, made) in intervention.neighbourhood_changes.iter().zip(made.iter_mut()) {
+                    if let Some((increase, increase_in_capacity)) = change.increase_on_day(day - intervention.start_day) {
+                        let (supportiveness_made, capacity_made) = apply_change(
+                            &change.id, day, &increase, &increase_in_capacity, bounds,
+                            &mut supportiveness, &mut capacity, &mut violations);
+                        add_to(&mut made.supportiveness, &supportiveness_made);
+                        add_to(&mut made.capacity, &capacity_made);
+                    }
+                }
+            }
+        }
+    }
+
+    violations
+}
+
+/// Applies a change to the copy of a neighbourhood, recording any violations
+/// * Returns: The change that was made to supportiveness and capacity
+#[allow(clippy::too_many_arguments)]
+fn apply_change(
+    neighbourhood_id: &str,
+    day: u32,
+    increase_in_supportiveness: &HashMap<TransportMode, f32>,
+    increase_in_capacity: &HashMap<TransportMode, i64>,
+    bounds: Bounds,
+    supportiveness: &mut HashMap<String, HashMap<TransportMode, f32>>,
+    capacity: &mut HashMap<String, HashMap<TransportMode, u32>>,
+    violations: &mut Vec<Violation>) -> (HashMap<TransportMode, f32>, HashMap<TransportMode, i64>)
+{
+    let current_supportiveness = supportiveness
+        .get_mut(neighbourhood_id)
+        .expect("A neighbourhood in your intervention was not found");
+    let (new_supportiveness, out_of_bounds) =
+        add_supportiveness(current_supportiveness, increase_in_supportiveness, bounds.supportiveness);
+    let supportiveness_made = difference(&new_supportiveness, current_supportiveness, |v| v);
+    *current_supportiveness = new_supportiveness;
+    violations.extend(out_of_bounds.into_iter().map(|(mode, value)| Violation {
+        day, neighbourhood_id: neighbourhood_id.to_string(), mode, field: Field::Supportiveness, value
+    }));
+
+    let current_capacity = capacity
+        .get_mut(neighbourhood_id)
+        .expect("A neighbourhood in your intervention was not found");
+    let (new_capacity, out_of_bounds) = add_capacity(current_capacity, increase_in_capacity);
+    let capacity_made = difference(&new_capacity, current_capacity, i64::from);
+    *current_capacity = new_capacity;
+    violations.extend(out_of_bounds.into_iter().map(|(mode, value)| Violation {
+        day, neighbourhood_id: neighbourhood_id.to_string(), mode, field: Field::Capacity, value
+    }));
+
+    (supportiveness_made, capacity_made)
+}
+
+/// The change from an old value to a new value, for each mode in new
+fn difference<T: Copy, U: ::std::ops::Sub<Output = U> + Default>(
+    new: &HashMap<TransportMode, T>,
+    old: &HashMap<TransportMode, T>,
+    convert: fn(T) -> U) -> HashMap<TransportMode, U>
+{
+    new.iter()
+        .map(|(mode, &v)| (*mode, convert(v) - old.get(mode).map_or(U::default(), |&v| convert(v))))
+        .collect()
+}
+
+/// Adds each value of increase to total
+fn add_to<T: Copy + ::std::ops::Add<Output = T>>(total: &mut HashMap<TransportMode, T>, increase: &HashMap<TransportMode, T>) {
+    *total = union_of(total, increase, |v1, v2| v1 + v2);
+}
+
+/// This checks the intervention schedule of a scenario, reporting every violation
+/// according to the bounds policy of the scenario
+/// * scenario: The scenario to check
+/// * file: The file the scenario was loaded from
+/// * Returns: A problem for each violation of a field with the Error policy
+pub fn check_interventions(scenario: &Scenario, file: &str) -> Vec<Problem> {
+    let mut problems = Vec::new();
+
+    for violation in find_violations(scenario) {
+        let policy = match violation.field {
+            Field::Supportiveness => scenario.bounds.supportiveness,
+            Field::Capacity => scenario.bounds.capacity
+        };
+
+        match policy {
+            BoundsPolicy::Error => problems.push(Problem::new(file, "interventions", violation.to_string())),
+            BoundsPolicy::Clamp => info!("Scenario {}, {}, it will be clamped", scenario.id, violation),
+            BoundsPolicy::Warn => warn!("Scenario {}, {}", scenario.id, violation)
+        }
+    }
+
+    problems
+}
@@ -1,49 +1,270 @@
-use rand;
+use rand::Rng;
 use std::collections::HashMap;
+use std::fs::File;
+use std::io::prelude::*;
+use serde_yaml;
+use transport_mode::TransportMode;
+use rainfall;
+use error::Error;
+use error::Problem;
 
-/// The weather for a given day
-#[derive(Eq, Hash, PartialEq, Copy, Clone)]
-pub enum Weather {
-    Good,
-    Bad
+/// The weather for a given day, one of the states of the WeatherModel
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct Weather {
+    /// The ID of the weather state, e.g. Dry, Light Rain, Heavy Rain or Snow
+    pub id: String,
+
+    /// How strongly the weather discourages each transport mode, from 0-1, an agent's
+    /// weather sensitivity is multiplied by this, modes that are not listed are not discouraged
+    #[serde(default)]
+    pub discouragement: HashMap<TransportMode, f32>,
+
+    /// The least rainfall (mm) on a day for it to be in this state, when replaying historical
+    /// rainfall, states without a minimum are never used when replaying
+    #[serde(default)]
+    pub minimum_rainfall: Option<f64>,
+
+    /// The position of the state in the WeatherModel, this is reported in the output
+    #[serde(skip)]
+    pub index: usize
+}
+
+/// Where the weather pattern comes from
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub enum WeatherSource {
+    /// Generate the weather using the Markov Chain
+    #[default]
+    MarkovChain,
+    /// Replay a historical daily rainfall series, classifying each day using minimum_rainfall
+    Historical {
+        /// A CSV file of date,rainfall, see rainfall::read_rainfall
+        file: String,
+        /// The date (YYYY-MM-DD) of day 0 of the simulation
+        start_date: String
+    }
+}
+
+/// How weather patterns are shared between the simulations of a run  
+/// Comparing the variance between simulations of runs with each gives the variance driven by the weather,
+/// and the variance driven by the population
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum WeatherRealisations {
+    /// Every simulation uses the same weather pattern
+    #[default]
+    Shared,
+    /// Each simulation generates its own weather pattern, from its own stream of random numbers
+    Independent
+}
+
+/// A Markov Chain weather model
+#[derive(Clone, Serialize, Deserialize)]
+pub struct WeatherModel {
+    /// Where the weather pattern comes from
+    #[serde(default)]
+    pub source: WeatherSource,
+
+    /// Whether the simulations share a weather pattern
+    #[serde(default)]
+    pub realisations: WeatherRealisations,
+
+    /// The states the weather can be in
+    pub states: Vec<Weather>,
+
+    /// The chance of each weather state (by id) on day 0
+    pub initial_probabilities: HashMap<String, f64>,
+
+    /// This defines if in state x, the chance of moving to state y is z, where
+    /// x & y are weather state ids that may be equal
+    pub transition_matrix: HashMap<String, HashMap<String, f64>>,
+
+    /// Optional, this makes the Markov Chain second order, if on the day before yesterday the
+    /// weather was in state w, and yesterday in state x, the chance of moving to state y is z, where
+    /// w -> x -> y -> z are elements of the HashMap. Where w -> x is not defined, transition_matrix is used
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub second_order_transition_matrix: HashMap<String, HashMap<String, HashMap<String, f64>>>
 }
 
-impl Weather {
-    /// This generates a weather pattern using a Markov Chain
-    /// * transition_matrix: This defines if in state x, the chance of moving to state y is z, where
-    /// x & y are weather conditions that may be equal
-    /// * chance_of_rain: The chance of rain on day 0
+impl WeatherModel {
+    /// Loads a WeatherModel from a file
+    /// * file: The YAML file storing the serialized weather model
+    /// * Returns: The created weather model, or an Error if the file could not be read or parsed
+    pub fn from_file(mut file: File) -> Result<Self, Error> {
+        info!("Loading weather model from file");
+        let mut file_contents = String::new();
+
+        file.read_to_string(&mut file_contents)?;
+
+        let mut weather_model: WeatherModel = serde_yaml::from_slice(file_contents.as_bytes())?;
+
+        for (i, state) in weather_model.states.iter_mut().enumerate() {
+            state.index = i;
+        }
+
+        Ok(weather_model)
+    }
+
+    /// Saves the WeatherModel to a file
+    /// * file: The YAML file to save it to
+    /// * Returns: Nothing if successful, otherwise the Error
+    pub fn save(&self, mut file: File) -> Result<(), Error> {
+        let weather_model_string = serde_yaml::to_string(self)?;
+        file.write_all(weather_model_string.as_bytes())?;
+        Ok(())
+    }
+
+    /// This generates a weather pattern from the source of the model
     /// * days: The number of days to generate
+    /// * rng: The random number generator
+    /// * Returns: A Vec<Weather> where the index is the day, or an Error if historical rainfall cannot be replayed
+    pub fn make_pattern<R: Rng>(&self, days: usize, rng: &mut R) -> Result<Vec<Weather>, Error> {
+        match self.source {
+            WeatherSource::MarkovChain => Ok(self.generate_pattern(days, rng)),
+            WeatherSource::Historical { ref file, ref start_date } => self.replay_rainfall(file, start_date, days)
+        }
+    }
+
+    /// Classify a day's rainfall as a weather state
+    /// * rainfall: The rainfall in mm
+    /// * Returns: The state with the highest minimum_rainfall, that is no more than rainfall, or None if there is no such state
+    pub fn classify(&self, rainfall: f64) -> Option<&Weather> {
+        self.states
+            .iter()
+            .filter(|state| state.minimum_rainfall.is_some_and(|minimum| minimum <= rainfall))
+            .fold(None, |chosen: Option<&Weather>, state| match chosen {
+                Some(chosen) if chosen.minimum_rainfall >= state.minimum_rainfall => Some(chosen),
+                _ => Some(state)
+            })
+    }
+
+    /// This replays a historical rainfall series as a weather pattern  
+    /// Days without rainfall take the weather of the previous day, if the series ends before
+    /// the pattern is complete, it starts again from start_date
+    /// * rainfall_file: The path of a CSV file of date,rainfall, see rainfall::read_rainfall
+    /// * start_date: The date (YYYY-MM-DD) of day 0
+    /// * days: The number of days to replay
+    /// * Returns: A Vec<Weather> where the index is the day, or an Error if a date that is replayed has no row,
+    ///   or its rainfall cannot be classified
+    fn replay_rainfall(&self, rainfall_file: &str, start_date: &str, days: usize) -> Result<Vec<Weather>, Error> {
+        let invalid = |message: String| Error::Invalid(vec![Problem::new(rainfall_file, "", message)]);
+
+        let start = rainfall::day_number(start_date)
+            .ok_or_else(|| invalid(format!("the start_date {} is not a valid date", start_date)))?;
+
+        // Index the series by the day number of each date
+        let all_days = rainfall::read_rainfall(rainfall_file)?;
+        let series: Vec<(i64, &rainfall::Rainfall)> = all_days
+            .iter()
+            .filter_map(|day| rainfall::day_number(&day.date).map(|day_number| (day_number, day)))
+            .filter(|&(day_number, _)| day_number >= start)
+            .collect();
+
+        let last = match series.last() {
+            Some(&(day_number, _)) => day_number,
+            None => return Err(invalid(format!("there is no rainfall on or after {}", start_date)))
+        };
+
+        // The number of days of the series that are replayed, before it starts again
+        let replayed = days.min((last - start + 1) as usize);
+        if replayed < days {
+            warn!("The rainfall series has {} days from {}, it will be repeated to make {} days",
+                replayed, start_date, days);
+        }
+
+        // Every date that is replayed needs a row
+        let mut problems = Vec::new();
+        let mut expected = start;
+        for &(day_number, day) in series.iter().take_while(|&&(day_number, _)| day_number < start + replayed as i64) {
+            if day_number < expected {
+                problems.push(Problem::new(rainfall_file, "", format!("{} is repeated", day.date)));
+            } else if day_number > expected {
+                problems.push(Problem::new(rainfall_file, "", format!(
+                    "{} day(s) are missing before {}", day_number - expected, day.date)));
+            }
+            expected = day_number + 1;
+        }
+
+        if !problems.is_empty() {
+            return Err(Error::Invalid(problems));
+        }
+
+        let mut pattern: Vec<Weather> = Vec::with_capacity(days);
+        for (i, &(_, day)) in series.iter().take(replayed).cycle().take(days).enumerate() {
+            let weather = match day.rainfall {
+                None if i > 0 => Some(pattern[i - 1].clone()),
+                rainfall => self.classify(rainfall.unwrap_or(0.0)).cloned()
+            };
+
+            match weather {
+                Some(weather) => pattern.push(weather),
+                None => problems.push(Problem::new(rainfall_file, "", format!(
+                    "no weather state has a minimum_rainfall low enough for the rainfall on {}", day.date)))
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(pattern)
+        } else {
+            Err(Error::Invalid(problems))
+        }
+    }
+
+    /// This generates a weather pattern using the Markov Chain
+    /// * days: The number of days to generate
+    /// * rng: The random number generator
     /// * Returns: A Vec<Weather> where the index is the day
-    pub fn make_pattern(
-        transition_matrix: HashMap<Weather, HashMap<Weather, f64>>,
-        chance_of_rain: f64,
-        days: usize) -> Vec<Self> 
-    {
+    fn generate_pattern<R: Rng>(&self, days: usize, rng: &mut R) -> Vec<Weather> {
         // Create an empty weather pattern
-        let mut pattern = Vec::with_capacity(days);
-        // On day 0, calculate if their is rain
-        if rand::random::<f64>() > chance_of_rain {
-            pattern.push(Weather::Good);
-        } else {
-            pattern.push(Weather::Bad);
+        let mut pattern: Vec<Weather> = Vec::with_capacity(days);
+
+        // On day 0, choose the weather from the initial probabilities
+        if days > 0 {
+            pattern.push(self.choose_state(&self.initial_probabilities, rng).clone());
         }
 
-        // For each day
+        // For each day, using the weather from the previous day (and the day before if
+        // the chain is second order), choose the weather from the transition matrix
         for i in 1..days {
-            // Using the weather from the previous day,
-            // get the probability of good weather, 
-            // calculate a random float, if this is less
-            // than that probability then the weather for day i
-            // is good
-            if rand::random::<f64>() < 
-                *transition_matrix.get(&pattern[i - 1]).unwrap()
-                .get(&Weather::Good).unwrap() {
-                    pattern.push(Weather::Good);
-                } else {
-                    pattern.push(Weather::Bad);
-                }
+            let second_order_transitions = if i >= 2 {
+                self.second_order_transition_matrix
+                    .get(&pattern[i - 2].id)
+                    .and_then(|transition_matrix| transition_matrix.get(&pattern[i - 1].id))
+            } else {
+                None
+            };
+
+            let transitions = second_order_transitions
+                .or_else(|| self.transition_matrix.get(&pattern[i - 1].id))
+                .expect("A weather state is missing from the transition matrix");
+            let weather = self.choose_state(transitions, rng).clone();
+            pattern.push(weather);
         }
+
         pattern
     }
-}
\ No newline at end of file
+
+    /// Choose a weather state at random
+    /// * probabilities: The chance of each state, by id, states that are not listed have no chance
+    /// * rng: The random number generator
+    /// * Returns: The chosen state
+    fn choose_state<R: Rng>(&self, probabilities: &HashMap<String, f64>, rng: &mut R) -> &Weather {
+        // Generate a number between 0 and 1
+        let random_number = rng.gen::<f64>();
+
+        // The states are considered in the order they are defined, so the same random number
+        // always gives the same state
+        let mut cdf = 0.0f64;
+        let mut chosen_state = None;
+        for state in self.states.iter() {
+            if let Some(probability) = probabilities.get(&state.id) {
+                cdf += *probability;
+                chosen_state = Some(state);
+                if random_number < cdf {
+                    break;
+                }
+            }
+        }
+
+        // If the probabilities sum to slightly less than 1, the last state with a chance is chosen
+        chosen_state.expect("A weather state has no probabilities of moving to any state")
+    }
+}
@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+use weather::WeatherModel;
+use weather::WeatherSource;
+use weather::WeatherRealisations;
+use rainfall;
+use rainfall::Rainfall;
+use error::Error;
+use error::Problem;
+
+/// Estimate a Markov Chain weather model from an observed daily rainfall series  
+/// Each day is classified using the minimum_rainfall of the states in template, the initial probabilities
+/// are the proportion of days in each state, and the transition matrices are the proportion of
+/// transitions from each state (or pair of states) to each state. Transitions to or from missing days, and across
+/// gaps in the dates, are ignored. Every state of template is in the fitted model, a state that is never left
+/// (or never observed) moves with the initial probabilities
+/// * series: The rainfall series, sorted by date
+/// * template: The weather model whose states (and their discouragement and minimum_rainfall) are used
+/// * order: The order of the chain, 1 uses the previous day's weather, 2 also uses the day before
+/// * rainfall_file: The file the series was read from
+/// * Returns: The fitted weather model, using the MarkovChain source, or an Error if there are no observed days,
+///   or a day's rainfall cannot be classified
+pub fn fit_weather_model(series: &[Rainfall], template: &WeatherModel, order: u32, rainfall_file: &str) -> Result<WeatherModel, Error> {
+    // Classify each day, None if the day is missing
+    let mut problems = Vec::new();
+    let days: Vec<(Option<i64>, Option<String>)> = series
+        .iter()
+        .map(|day| (
+            rainfall::day_number(&day.date),
+            day.rainfall.and_then(|rainfall| match template.classify(rainfall) {
+                Some(state) => Some(state.id.clone()),
+                None => {
+                    problems.push(Problem::new(rainfall_file, "", format!(
+                        "no weather state has a minimum_rainfall low enough for the rainfall on {}", day.date)));
+                    None
+                }
+            })))
+        .collect();
+
+    if !problems.is_empty() {
+        return Err(Error::Invalid(problems));
+    }
+
+    // Whether each day follows the one before it in the series
+    let consecutive = |window: &[(Option<i64>, Option<String>)]| window
+        .windows(2)
+        .all(|pair| match (pair[0].0, pair[1].0) {
+            (Some(yesterday), Some(today)) => today == yesterday + 1,
+            _ => false
+        });
+
+    // Count the days in each state
+    let mut state_counts: HashMap<String, f64> = HashMap::new();
+    for day in days.iter().flat_map(|day| day.1.iter()) {
+        *state_counts.entry(day.clone()).or_insert(0.0) += 1.0;
+    }
+
+    if state_counts.is_empty() {
+        return Err(Error::Invalid(vec![
+            Problem::new(rainfall_file, "", "there are no observed days to fit the weather model to")]));
+    }
+
+    // Count the transitions between consecutive days
+    let mut transition_counts: HashMap<String, HashMap<String, f64>> = HashMap::new();
+    for window in days.windows(2).filter(|window| consecutive(window)) {
+        if let (Some(yesterday), Some(today)) = (&window[0].1, &window[1].1) {
+            *transition_counts
+                .entry(yesterday.clone()).or_default()
+                .entry(today.clone()).or_insert(0.0) += 1.0;
+        }
+    }
+
+    let state_ids: Vec<&String> = template.states.iter().map(|state| &state.id).collect();
+    let initial_probabilities = normalise(&state_counts, &state_ids);
+
+    // A state that is never observed before another day moves with the initial probabilities
+    let transition_matrix: HashMap<String, HashMap<String, f64>> = state_ids
+        .iter()
+        .map(|&state| (state.clone(), match transition_counts.get(state) {
+            Some(counts) => normalise(counts, &state_ids),
+            None => initial_probabilities.clone()
+        }))
+        .collect();
+
+    // Count the transitions from each pair of consecutive days
+    let mut second_order_transition_matrix = HashMap::new();
+    if order >= 2 {
+        let mut second_order_counts: HashMap<String, HashMap<String, HashMap<String, f64>>> = HashMap::new();
+        for window in days.windows(3).filter(|window| consecutive(window)) {
+            if let (Some(day_before), Some(yesterday), Some(today)) = (&window[0].1, &window[1].1, &window[2].1) {
+                *second_order_counts
+                    .entry(day_before.clone()).or_default()
+                    .entry(yesterday.clone()).or_default()
+                    .entry(today.clone()).or_insert(0.0) += 1.0;
+            }
+        }
+
+        second_order_transition_matrix = second_order_counts
+            .iter()
+            .map(|(day_before, yesterdays)| (
+                day_before.clone(),
+                yesterdays
+                    .iter()
+                    .map(|(yesterday, counts)| (yesterday.clone(), normalise(counts, &state_ids)))
+                    .collect()))
+            .collect();
+    }
+
+    info!("Fitted weather model to {} days of rainfall", state_counts.values().sum::<f64>());
+
+    Ok(WeatherModel {
+        source: WeatherSource::MarkovChain,
+        realisations: WeatherRealisations::Shared,
+        states: template.states.clone(),
+        initial_probabilities,
+        transition_matrix,
+        second_order_transition_matrix
+    })
+}
+
+/// Divide each count by the total of the counts
+/// * counts: A Map: State ID -> count
+/// * state_ids: The IDs of every state, states without a count have a proportion of 0
+/// * Returns: A Map: State ID -> proportion
+fn normalise(counts: &HashMap<String, f64>, state_ids: &[&String]) -> HashMap<String, f64> {
+    let total: f64 = counts.values().sum();
+    state_ids
+        .iter()
+        .map(|&state| (state.clone(), counts.get(state).unwrap_or(&0.0) / total))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use weather::Weather;
+
+    /// A template with Dry, Rain (1mm or more) and Heavy (10mm or more) states
+    fn template() -> WeatherModel {
+        WeatherModel {
+            source: WeatherSource::MarkovChain,
+            realisations: WeatherRealisations::Shared,
+            states: [("Dry", 0.0), ("Rain", 1.0), ("Heavy", 10.0)]
+                .iter()
+                .enumerate()
+                .map(|(index, &(id, minimum_rainfall))| Weather {
+                    id: id.to_string(),
+                    discouragement: HashMap::new(),
+                    minimum_rainfall: Some(minimum_rainfall),
+                    index
+                })
+                .collect(),
+            initial_probabilities: HashMap::new(),
+            transition_matrix: HashMap::new(),
+            second_order_transition_matrix: HashMap::new()
+        }
+    }
+
+    /// Dry, Rain, Rain, then a gap, then Dry, Dry, a missing day and Rain
+    fn series() -> Vec<Rainfall> {
+        [("2020-01-01", Some(0.0)), ("2020-01-02", Some(2.0)), ("2020-01-03", Some(5.0)),
+         ("2020-01-05", Some(0.5)), ("2020-01-06", Some(0.0)), ("2020-01-07", None), ("2020-01-08", Some(3.0))]
+            .iter()
+            .map(|&(date, rainfall)| Rainfall { date: date.to_string(), rainfall })
+            .collect()
+    }
+
+    #[test]
+    fn first_order_transitions_are_split_at_gaps() {
+        let fitted = fit_weather_model(&series(), &template(), 1, "rainfall.csv").unwrap();
+
+        assert_eq!(fitted.initial_probabilities["Dry"], 0.5);
+        assert_eq!(fitted.initial_probabilities["Rain"], 0.5);
+        assert_eq!(fitted.initial_probabilities["Heavy"], 0.0);
+
+        // Rain -> Dry across the gap, and the transitions to and from the missing day, are not counted
+        assert_eq!(fitted.transition_matrix["Dry"]["Dry"], 0.5);
+        assert_eq!(fitted.transition_matrix["Dry"]["Rain"], 0.5);
+        assert_eq!(fitted.transition_matrix["Rain"]["Rain"], 1.0);
+        assert_eq!(fitted.transition_matrix["Rain"]["Dry"], 0.0);
+
+        // Heavy is never observed, so it moves with the initial probabilities
+        assert_eq!(fitted.transition_matrix["Heavy"], fitted.initial_probabilities);
+        assert!(fitted.second_order_transition_matrix.is_empty());
+    }
+
+    #[test]
+    fn second_order_transitions_count_consecutive_triples() {
+        let fitted = fit_weather_model(&series(), &template(), 2, "rainfall.csv").unwrap();
+
+        // Only Dry -> Rain -> Rain has three consecutive observed days
+        assert_eq!(fitted.second_order_transition_matrix.len(), 1);
+        assert_eq!(fitted.second_order_transition_matrix["Dry"].len(), 1);
+        let counts = &fitted.second_order_transition_matrix["Dry"]["Rain"];
+        assert_eq!(counts["Rain"], 1.0);
+        assert_eq!(counts["Dry"], 0.0);
+        assert_eq!(counts["Heavy"], 0.0);
+    }
+
+    #[test]
+    fn a_series_without_observations_is_reported() {
+        let series = vec![Rainfall { date: "2020-01-01".to_string(), rainfall: None }];
+        assert!(fit_weather_model(&series, &template(), 1, "rainfall.csv").is_err());
+    }
+}